@@ -1,62 +1,277 @@
-use sdl2::rect::{Point, Rect};
-use sdl2::render::{WindowCanvas};
-use sdl2::pixels::Color;
 use crate::vector::{Vector};
-use sdl2::gfx::primitives::DrawRenderer;
-use crate::render_gl::Program;
-use sdl2::ttf::get_linked_version;
-use crate::{HEIGHT, WIDTH};
+use crate::{BOAT_ACCELERATION, BOAT_MAX_SPEED, BOAT_TURN_RATE, BOAT_DRAG, BOAT_LATERAL_DRAG, BOAT_MASS, BOAT_BOW_WINDAGE, BOAT_BEAM_WINDAGE};
+use crate::{PRESSURE_SMOOTHING, PACK_PRESSURE_LIMIT, BESET_TICKS, CRUSH_TICKS, CRUSH_DAMAGE_RATE};
+use crate::{HULL_DAMAGE_THRESHOLD, HULL_DAMAGE_PER_ENERGY, DAMAGED_MIN_SPEED_FACTOR, BOAT_FUEL_CAPACITY, FUEL_BURN_RATE, FUEL_LOW_LEVEL};
+use crate::physics::{Body, RigidBody, cross};
+use crate::entity::EntityId;
+use serde::{Serialize, Deserialize};
 
-// Represents a discrete piece of ice
-#[derive(Debug, Clone)]
+// Parts of the hull that take damage separately, bow to stern
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum HullSection {
+    Bow,
+    Midships,
+    Stern,
+}
+
+// Integrity of each hull section, 1.0 when sound and 0.0 when breached
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Integrity {
+    pub bow: f32,
+    pub midships: f32,
+    pub stern: f32,
+}
+
+impl Integrity {
+    pub fn sound() -> Integrity {
+        return Integrity{bow: 1.0, midships: 1.0, stern: 1.0};
+    }
+
+    fn get_mut(&mut self, section: HullSection) -> &mut f32 {
+        return match section {
+            HullSection::Bow => &mut self.bow,
+            HullSection::Midships => &mut self.midships,
+            HullSection::Stern => &mut self.stern,
+        };
+    }
+
+    // Average over the whole hull
+    pub fn overall(&self) -> f32 {
+        return (self.bow + self.midships + self.stern) / 3.0;
+    }
+
+    // A single breached section floods the ship
+    pub fn is_breached(&self) -> bool {
+        return self.bow <= 0.0 || self.midships <= 0.0 || self.stern <= 0.0;
+    }
+}
+
+// The player's ship
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Boat {
+    pub id: EntityId,
+    // Velocity
     pub direction: Vector,
     pub position: Vector,
     pub size: u32,
+    // Radians clockwise from straight up the screen
+    pub heading: f32,
+    // -1.0 (full astern) to 1.0 (full ahead)
+    pub throttle: f32,
+    // -1.0 (hard to port) to 1.0 (hard to starboard)
+    pub rudder: f32,
+    pub integrity: Integrity,
+    // Tons of coal left in the bunkers
+    pub fuel: f32,
+    // Averaged compressive force from the surrounding ice
+    pub pressure: f32,
+    // Tuning ticks the pressure has been over the limit, counting back down once it eases
+    pub beset_time: f32,
+    // State at the start of the current tick, for render interpolation
+    pub prev_position: Vector,
+    pub prev_heading: f32,
+    // Hull outline rotated to the current heading, relative to position
     pub perimeter: Vec<Vector>,
+    // Hull outline in local space (bow pointing up)
+    hull: Vec<Vector>,
 }
 
 impl Boat {
 
-    pub fn new(position: Vector , size: u32) -> Boat {
-        let mut boat = Boat{direction: Vector{x: 0.0, y: -0.0}, position, size, perimeter: vec![] };
+    pub fn new(id: EntityId, position: Vector , size: u32) -> Boat {
+        let mut boat = Boat{
+            id,
+            direction: Vector{x: 0.0, y: -0.0},
+            position,
+            size,
+            heading: 0.0,
+            throttle: 0.0,
+            rudder: 0.0,
+            integrity: Integrity::sound(),
+            fuel: BOAT_FUEL_CAPACITY,
+            pressure: 0.0,
+            beset_time: 0.0,
+            prev_position: position,
+            prev_heading: 0.0,
+            perimeter: vec![],
+            hull: vec![],
+        };
         boat.init_perimeter();
         return boat;
     }
 
+    // Unit vector pointing out of the bow
+    pub fn forward(&self) -> Vector {
+        return Vector{x: 0.0, y: -1.0}.rotate(self.heading);
+    }
+
+    // Bounding circle radius around position
+    pub fn radius(&self) -> f32 {
+        return self.hull.iter().map(|p| p.length()).fold(0.0, f32::max);
+    }
+
+    // Which part of the hull a world space point is alongside
+    pub fn section_at(&self, point: &Vector) -> HullSection {
+        // Hull runs from 3 * size ahead of position to 2 * size astern
+        let along = point.sub(&self.position).dot(&self.forward());
+        let size = self.size as f32;
+        if along > size {
+            return HullSection::Bow;
+        }
+        if along < -size {
+            return HullSection::Stern;
+        }
+        return HullSection::Midships;
+    }
+
+    // Damages the section of hull at point. Knocks below threshold energy do nothing.
+    pub fn damage(&mut self, point: &Vector, energy: f32) {
+        let damage = (energy - HULL_DAMAGE_THRESHOLD).max(0.0) * HULL_DAMAGE_PER_ENERGY;
+        let section = self.integrity.get_mut(self.section_at(point));
+        *section = (*section - damage).max(0.0);
+    }
+
+    // Fraction of full power the engine can make on the coal that's left
+    pub fn engine_output(&self) -> f32 {
+        return (self.fuel / FUEL_LOW_LEVEL).clamp(0.0, 1.0);
+    }
+
+    // Loads coal, up to what the bunkers hold. Returns how much was taken on.
+    pub fn refuel(&mut self, amount: f32) -> f32 {
+        let taken = amount.min(BOAT_FUEL_CAPACITY - self.fuel).max(0.0);
+        self.fuel += taken;
+        return taken;
+    }
+
+    // Takes this tick's compressive force from the ice. Sustained pressure first traps the ship,
+    // then starts crushing her amidships.
+    pub fn update_pressure(&mut self, compression: f32, steps: f32) {
+        let keep = PRESSURE_SMOOTHING.powf(steps);
+        self.pressure = self.pressure * keep + compression * (1.0 - keep);
+        if self.pressure > PACK_PRESSURE_LIMIT {
+            self.beset_time += steps;
+        } else {
+            self.beset_time = (self.beset_time - steps).max(0.0);
+        }
+        if self.beset_time > CRUSH_TICKS {
+            let damage = (self.pressure - PACK_PRESSURE_LIMIT).max(0.0) * CRUSH_DAMAGE_RATE * steps;
+            self.integrity.midships = (self.integrity.midships - damage).max(0.0);
+        }
+    }
+
+    // Held fast by the ice, the engine can't move her
+    pub fn is_beset(&self) -> bool {
+        return self.beset_time > BESET_TICKS;
+    }
+
+    pub fn is_sunk(&self) -> bool {
+        return self.integrity.is_breached();
+    }
+
+    // Area the wind catches when blowing from the given direction, smallest bow or stern on
+    pub fn wind_profile(&self, wind: &Vector) -> f32 {
+        let speed = wind.length();
+        if speed == 0.0 {
+            return 0.0;
+        }
+        let abeam = cross(&self.forward(), wind).abs() / speed;
+        return self.size as f32 * (BOAT_BOW_WINDAGE + (BOAT_BEAM_WINDAGE - BOAT_BOW_WINDAGE) * abeam);
+    }
+
+    pub fn save_state(&mut self) {
+        self.prev_position = self.position;
+        self.prev_heading = self.heading;
+    }
+
+    // Position and heading alpha of the way from the previous tick to the current one
+    pub fn interpolated(&self, alpha: f32) -> (Vector, f32) {
+        let position = self.prev_position.add(&self.position.sub(&self.prev_position).mul(alpha));
+        let heading = self.prev_heading + (self.heading - self.prev_heading) * alpha;
+        return (position, heading);
+    }
+
+    // Turns and accelerates the boat using the current throttle and rudder settings. Drag,
+    // steering and top speed all work on the boat's speed through the water, so a current carries
+    // the boat along. steps is the length of the update in tuning ticks (see TUNING_RATE).
+    // World moves the boat afterwards, so the move can be swept against the ice.
+    pub fn update(&mut self, steps: f32, water: &Vector) {
+        let forward = self.forward();
+        let relative = self.direction.sub(water);
+
+        // The rudder only bites when water is flowing past it, so turning scales with speed
+        // (and reverses when going astern)
+        let forward_speed = relative.dot(&forward);
+        self.heading += self.rudder * BOAT_TURN_RATE * forward_speed / BOAT_MAX_SPEED * steps;
+        self.update_perimeter();
+
+        // Split velocity into along-keel and sideways components and drag them separately
+        let forward = self.forward();
+        let forward_speed = relative.dot(&forward);
+        let along = forward.mul(forward_speed);
+        let lateral = relative.sub(&along);
+        let mut relative = along.mul(BOAT_DRAG.powf(steps)).add(&lateral.mul(BOAT_LATERAL_DRAG.powf(steps)));
+
+        // Throttle pushes along the heading. A damaged hull takes on water and drags, so both
+        // acceleration and top speed drop with integrity. A sunk or beset ship makes no way at all.
+        let condition = if self.is_sunk() || self.is_beset() {
+            0.0
+        } else {
+            DAMAGED_MIN_SPEED_FACTOR + (1.0 - DAMAGED_MIN_SPEED_FACTOR) * self.integrity.overall()
+        };
+        // Thrust also needs coal, which burns in proportion to the throttle
+        let output = self.engine_output();
+        relative = relative.add(&forward.mul(self.throttle * BOAT_ACCELERATION * condition * output * steps));
+        self.fuel = (self.fuel - self.throttle.abs() * FUEL_BURN_RATE * steps).max(0.0);
+
+        let max_speed = BOAT_MAX_SPEED * condition;
+        let speed = relative.length();
+        if speed > max_speed {
+            relative = relative.mul(max_speed / speed);
+        }
+        self.direction = water.add(&relative);
+    }
+
+    fn update_perimeter(&mut self) {
+        let heading = self.heading;
+        self.perimeter = self.hull.iter().map(|p| p.rotate(heading)).collect();
+    }
+
     // TODO: No longer matches graphic boat
     fn init_perimeter(&mut self) {
         let l1_p1_x  = 0.0 - (self.size * 1) as f32;
         let l1_p1_y  = 0.0 - (self.size * 1) as f32;
-        self.perimeter.push(Vector{x: l1_p1_x, y: l1_p1_y});
+        self.hull.push(Vector{x: l1_p1_x, y: l1_p1_y});
 
         let l1_p2_x = 0.0;
         let l1_p2_y = 0.0 - (self.size * 3) as f32;
-        self.perimeter.push(Vector{x: l1_p2_x, y: l1_p2_y});
+        self.hull.push(Vector{x: l1_p2_x, y: l1_p2_y});
 
         let l2_p1_x  = (self.size * 1) as f32;
         let l2_p1_y  = 0.0 - (self.size * 1) as f32;
-        self.perimeter.push(Vector{x: l2_p1_x, y: l2_p1_y});
+        self.hull.push(Vector{x: l2_p1_x, y: l2_p1_y});
 
         let l3_p2_x = (self.size * 1) as f32;
         let l3_p2_y = (self.size * 1) as f32;
-        self.perimeter.push(Vector{x: l3_p2_x, y: l3_p2_y});
+        self.hull.push(Vector{x: l3_p2_x, y: l3_p2_y});
 
         let l5_p2_x = (self.size / 2) as f32;
         let l5_p2_y = (self.size * 2) as f32;
-        self.perimeter.push(Vector{x: l5_p2_x, y: l5_p2_y});
+        self.hull.push(Vector{x: l5_p2_x, y: l5_p2_y});
 
         let l7_p1_x = 0.0 - (self.size / 2) as f32;
         let l7_p1_y = (self.size * 2) as f32;
-        self.perimeter.push(Vector{x: l7_p1_x, y: l7_p1_y});
+        self.hull.push(Vector{x: l7_p1_x, y: l7_p1_y});
 
         let l6_p1_x  = 0.0 - (self.size * 1) as f32;
         let l6_p1_y  = 0.0 + (self.size * 1) as f32;
-        self.perimeter.push(Vector{x: l6_p1_x, y: l6_p1_y});
+        self.hull.push(Vector{x: l6_p1_x, y: l6_p1_y});
+        self.update_perimeter();
     }
 
-    pub fn get_vertices(&self, offset: &Vector) -> Vec<f32> {
+    // World space triangles, as interleaved xyz rgb vertices
+    pub fn get_vertices(&self, alpha: f32) -> Vec<f32> {
         let mut ret = Vec::new();
+        let (position, heading) = self.interpolated(alpha);
 
         // The boat, like the icebergs, is composed of triangles
 
@@ -120,38 +335,45 @@ impl Boat {
         trigons.push(rear);
 
 
-        // TODO: Can be refactored - logic mostly duplicated from iceberg vertex conversion
+        // The hull reddens as the ice pressure on it builds, so the player can see it coming
+        let strain = (self.pressure / PACK_PRESSURE_LIMIT).min(1.0);
+        let color = [0.239 + (0.8 - 0.239) * strain, 0.172 * (1.0 - strain), 0.062 * (1.0 - strain)];
+
+        // The camera's view-projection takes these the rest of the way to the screen
         for trigon in trigons {
             for vertex in trigon {
-
-                // Offset-adjusted points (position relative to an origin in the upper left corner of the visible screen)
-                let pos_x = vertex.x + self.position.x - offset.x;
-                let mut pos_y = vertex.y + self.position.y - offset.y;
-                let pos_z = 0.0;
-
-                // NDC System has bottom left origin, so adjust our y value (top left origin) into that system
-                pos_y = HEIGHT as f32 - pos_y;
-
-                // Map these points into the normalized device coordinates space
-                let input_range = WIDTH as f32;
-                let output_range = 1.0 - -1.0;
-                let output_x = (pos_x - 0.0)*output_range / input_range + -1.0;
-
-                let input_range = HEIGHT as f32;
-                let output_range = 1.0 - -1.0;
-                let output_y = (pos_y - 0.0)*output_range / input_range + -1.0;
-                ret.push(output_x);
-                ret.push(output_y);
-                ret.push(pos_z);
+                let vertex = vertex.rotate(heading).add(&position);
+                ret.push(vertex.x);
+                ret.push(vertex.y);
+                ret.push(0.0);
 
                 // Colors
-                // 0.239, 0.172, 0.062
-                ret.push(0.239);
-                ret.push(0.172);
-                ret.push(0.062);
+                ret.push(color[0]);
+                ret.push(color[1]);
+                ret.push(color[2]);
             }
         }
         return ret;
     }
 }
 
+// The boat's heading is steered by the rudder alone, so collisions only change its velocity
+impl RigidBody for Boat {
+    fn body(&self) -> Body {
+        return Body{
+            position: self.position,
+            velocity: self.direction,
+            angular_velocity: 0.0,
+            inv_mass: 1.0 / BOAT_MASS,
+            inv_inertia: 0.0,
+        };
+    }
+
+    fn apply_impulse(&mut self, impulse: &Vector, _contact: &Vector) {
+        self.direction = self.direction.add(&impulse.mul(1.0 / BOAT_MASS));
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        self.position = self.position.add(offset);
+    }
+}
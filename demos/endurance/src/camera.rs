@@ -0,0 +1,114 @@
+use crate::vector::Vector;
+use crate::{CAMERA_STIFFNESS, CAMERA_LOOK_AHEAD, CAMERA_MIN_ZOOM, CAMERA_MAX_ZOOM, CAMERA_ZOOM_STEP};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CameraMode {
+    // Tracks the boat, looking ahead of where it's going
+    Follow,
+    // Stays put until dragged
+    FreePan,
+}
+
+// Where the world is looked at from. Screen space is pixels from the top left of the window,
+// with y down like world space.
+pub struct Camera {
+    // World space point at the center of the screen
+    pub position: Vector,
+    // Screen pixels per world unit
+    pub zoom: f32,
+    // Radians clockwise, the world turns the other way on screen
+    pub rotation: f32,
+    pub mode: CameraMode,
+    viewport_width: f32,
+    viewport_height: f32,
+}
+
+impl Camera {
+    pub fn new(position: Vector, viewport_width: u32, viewport_height: u32) -> Camera {
+        return Camera{
+            position,
+            zoom: 1.0,
+            rotation: 0.0,
+            mode: CameraMode::Follow,
+            viewport_width: viewport_width.max(1) as f32,
+            viewport_height: viewport_height.max(1) as f32,
+        };
+    }
+
+    // Call when the window changes size. The camera stays centered on the same point, and since
+    // zoom is in screen pixels per world unit, a bigger window shows more of the world rather
+    // than stretching it.
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        self.viewport_width = width.max(1) as f32;
+        self.viewport_height = height.max(1) as f32;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            CameraMode::Follow => CameraMode::FreePan,
+            CameraMode::FreePan => CameraMode::Follow,
+        };
+    }
+
+    // Eases towards a point ahead of target along its velocity. dt is real seconds since the last
+    // frame, so the camera moves the same at any frame rate. Does nothing while panning freely.
+    pub fn follow(&mut self, target: &Vector, velocity: &Vector, dt: f32) {
+        if self.mode != CameraMode::Follow {
+            return;
+        }
+        let ahead = target.add(&velocity.mul(CAMERA_LOOK_AHEAD));
+        let fraction = 1.0 - (-CAMERA_STIFFNESS * dt).exp();
+        self.position = self.position.add(&ahead.sub(&self.position).mul(fraction));
+    }
+
+    // Moves the camera so the world point under from ends up under to (both screen space)
+    pub fn pan(&mut self, from: &Vector, to: &Vector) {
+        let moved = self.screen_to_world(from).sub(&self.screen_to_world(to));
+        self.position = self.position.add(&moved);
+    }
+
+    // Zooms in by notches of the wheel, keeping the world point under the cursor where it is
+    pub fn zoom_at(&mut self, screen: &Vector, notches: i32) {
+        let before = self.screen_to_world(screen);
+        self.zoom = (self.zoom * CAMERA_ZOOM_STEP.powi(notches)).clamp(CAMERA_MIN_ZOOM, CAMERA_MAX_ZOOM);
+        let after = self.screen_to_world(screen);
+        self.position = self.position.add(&before.sub(&after));
+    }
+
+    pub fn screen_to_world(&self, screen: &Vector) -> Vector {
+        let center = Vector{x: self.viewport_width / 2.0, y: self.viewport_height / 2.0};
+        return screen.sub(&center).mul(1.0 / self.zoom).rotate(self.rotation).add(&self.position);
+    }
+
+    // Corners of the axis aligned box around everything on screen, in world space
+    pub fn visible_bounds(&self) -> (Vector, Vector) {
+        let corners = [
+            self.screen_to_world(&Vector{x: 0.0, y: 0.0}),
+            self.screen_to_world(&Vector{x: self.viewport_width, y: 0.0}),
+            self.screen_to_world(&Vector{x: 0.0, y: self.viewport_height}),
+            self.screen_to_world(&Vector{x: self.viewport_width, y: self.viewport_height}),
+        ];
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in &corners[1..] {
+            min = Vector{x: min.x.min(corner.x), y: min.y.min(corner.y)};
+            max = Vector{x: max.x.max(corner.x), y: max.y.max(corner.y)};
+        }
+        return (min, max);
+    }
+
+    // Column major matrix taking world space straight to normalized device coordinates:
+    // relative to the camera, turned against its rotation, scaled by zoom and flipped so y is up
+    pub fn view_projection(&self) -> [f32; 16] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let sx = 2.0 * self.zoom / self.viewport_width;
+        let sy = -2.0 * self.zoom / self.viewport_height;
+        let (cx, cy) = (self.position.x, self.position.y);
+        return [
+            sx * cos, -sy * sin, 0.0, 0.0,
+            sx * sin, sy * cos, 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            -sx * (cos * cx + sin * cy), -sy * (-sin * cx + cos * cy), 0.0, 1.0,
+        ];
+    }
+}
@@ -0,0 +1,56 @@
+use rand::Rng;
+
+// Command line options, e.g.
+//   endurance --headless --ticks 1000 --seed 42
+//   endurance --record session.replay
+//   endurance --headless --replay session.replay
+//   endurance --headless --ticks 500 --save world.json
+//   endurance --load world.json
+//   endurance --currents weddell.grid
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub headless: bool,
+    pub ticks: u32,
+    // Random unless given, so every run can be reproduced with the printed seed
+    pub seed: u64,
+    // Save the session's input to this file
+    pub record: Option<String>,
+    // Play back input (and seed) from this file
+    pub replay: Option<String>,
+    // Start from a world snapshot instead of generating one
+    pub load: Option<String>,
+    // Save a world snapshot when the run ends
+    pub save: Option<String>,
+    // Current velocity grid file, instead of random gyres
+    pub currents: Option<String>,
+}
+
+impl Options {
+    pub fn parse() -> Result<Options, String> {
+        let mut options = Options{headless: false, ticks: 1000, seed: rand::thread_rng().gen(), record: None, replay: None, load: None, save: None, currents: None};
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--ticks" => options.ticks = parse_value(&arg, args.next())?,
+                "--seed" => options.seed = parse_value(&arg, args.next())?,
+                "--record" => options.record = Some(parse_value(&arg, args.next())?),
+                "--replay" => options.replay = Some(parse_value(&arg, args.next())?),
+                "--load" => options.load = Some(parse_value(&arg, args.next())?),
+                "--save" => options.save = Some(parse_value(&arg, args.next())?),
+                "--currents" => options.currents = Some(parse_value(&arg, args.next())?),
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+        // Replays only store a seed, so they have to start from a generated world
+        if options.load.is_some() && (options.record.is_some() || options.replay.is_some()) {
+            return Err("--load can't be combined with --record or --replay".to_string());
+        }
+        return Ok(options);
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("{} expects a value", flag))?;
+    return value.parse().map_err(|_| format!("Invalid value for {}: {}", flag, value));
+}
@@ -0,0 +1,327 @@
+use crate::vector::Vector;
+
+// Contact manifold between two convex polygons
+#[derive(Debug, Clone)]
+pub struct Contact {
+    // Unit vector pointing from the first polygon towards the second
+    pub normal: Vector,
+    // How far the second polygon has to move along normal to separate them
+    pub depth: f32,
+    // World space points where the polygons touch
+    pub points: Vec<Vector>,
+}
+
+impl Contact {
+    // Single representative contact point, used for impulses
+    pub fn center(&self) -> Vector {
+        let sum = self.points.iter().fold(Vector{x: 0.0, y: 0.0}, |acc, p| acc.add(p));
+        return sum.mul(1.0 / self.points.len() as f32);
+    }
+}
+
+// Polygon edge, along with the vertex furthest along the query direction
+struct Edge {
+    start: Vector,
+    end: Vector,
+    max: Vector,
+}
+
+// Moves a local space outline to world space
+pub fn to_world(position: &Vector, perimeter: &[Vector]) -> Vec<Vector> {
+    return perimeter.iter().map(|p| p.add(position)).collect();
+}
+
+fn project(points: &[Vector], axis: &Vector) -> (f32, f32) {
+    let mut min = f32::MAX;
+    let mut max = f32::MIN;
+    for p in points {
+        let d = p.dot(axis);
+        min = min.min(d);
+        max = max.max(d);
+    }
+    return (min, max);
+}
+
+fn edge_normals(points: &[Vector]) -> Vec<Vector> {
+    let mut normals = Vec::new();
+    for i in 0..points.len() {
+        let edge = points[(i + 1) % points.len()].sub(&points[i]);
+        if edge.x != 0.0 || edge.y != 0.0 {
+            normals.push(Vector{x: -edge.y, y: edge.x}.norm());
+        }
+    }
+    return normals;
+}
+
+// Separating axis test for two convex polygons given in world space.
+// Returns None if they don't overlap.
+pub fn sat_collision(a: &[Vector], b: &[Vector]) -> Option<Contact> {
+    let mut depth = f32::MAX;
+    let mut normal = Vector{x: 0.0, y: 0.0};
+
+    let mut axes = edge_normals(a);
+    axes.append(&mut edge_normals(b));
+    for axis in axes {
+        let (min_a, max_a) = project(a, &axis);
+        let (min_b, max_b) = project(b, &axis);
+
+        // Distance b has to move in either direction along the axis to clear a. Taking the
+        // smaller one (rather than the overlap of the intervals) gives the right answer when
+        // one polygon sits entirely inside the other.
+        let forward = max_a - min_b;
+        let backward = max_b - min_a;
+        if forward <= 0.0 || backward <= 0.0 {
+            return None;
+        }
+        if forward < depth {
+            depth = forward;
+            normal = axis;
+        }
+        if backward < depth {
+            depth = backward;
+            normal = axis.mul(-1.0);
+        }
+    }
+
+    let points = contact_points(a, b, &normal);
+    return Some(Contact{normal, depth, points});
+}
+
+// Swept separating axis test: how far along motion (0.0 - 1.0) polygon a can move before it
+// touches b, if it touches at all. Exact for translation - rotation during the move is ignored.
+// Polygons that already overlap are left to sat_collision and give None.
+pub fn time_of_impact(a: &[Vector], b: &[Vector], motion: &Vector) -> Option<f32> {
+    let mut first: f32 = 0.0;
+    let mut last: f32 = 1.0;
+    let mut separated = false;
+
+    // For two translating convex polygons, their edge normals are still the only axes that matter
+    let mut axes = edge_normals(a);
+    axes.append(&mut edge_normals(b));
+    for axis in axes {
+        let (min_a, max_a) = project(a, &axis);
+        let (min_b, max_b) = project(b, &axis);
+        let speed = motion.dot(&axis);
+
+        if max_a <= min_b {
+            // a is behind b on this axis, it has to close the gap
+            separated = true;
+            if speed <= 0.0 {
+                return None;
+            }
+            first = first.max((min_b - max_a) / speed);
+            last = last.min((max_b - min_a) / speed);
+        } else if max_b <= min_a {
+            separated = true;
+            if speed >= 0.0 {
+                return None;
+            }
+            first = first.max((max_b - min_a) / speed);
+            last = last.min((min_b - max_a) / speed);
+        } else if speed > 0.0 {
+            last = last.min((max_b - min_a) / speed);
+        } else if speed < 0.0 {
+            last = last.min((min_b - max_a) / speed);
+        }
+
+        if first > last {
+            return None;
+        }
+    }
+
+    if !separated {
+        return None;
+    }
+    return Some(first);
+}
+
+// The edge of the polygon most perpendicular to direction, on the side facing direction
+fn best_edge(points: &[Vector], direction: &Vector) -> Edge {
+    let mut index = 0;
+    let mut furthest = f32::MIN;
+    for (i, p) in points.iter().enumerate() {
+        let d = p.dot(direction);
+        if d > furthest {
+            furthest = d;
+            index = i;
+        }
+    }
+
+    let max = points[index];
+    let next = points[(index + 1) % points.len()];
+    let prev = points[(index + points.len() - 1) % points.len()];
+    let to_next = next.sub(&max).norm();
+    let from_prev = max.sub(&prev).norm();
+    if from_prev.dot(direction).abs() <= to_next.dot(direction).abs() {
+        return Edge{start: prev, end: max, max};
+    }
+    return Edge{start: max, end: next, max};
+}
+
+// Keeps the part of segment v1-v2 where direction . p >= offset
+fn clip(v1: Vector, v2: Vector, direction: &Vector, offset: f32) -> Vec<Vector> {
+    let mut clipped = Vec::new();
+    let d1 = direction.dot(&v1) - offset;
+    let d2 = direction.dot(&v2) - offset;
+    if d1 >= 0.0 {
+        clipped.push(v1);
+    }
+    if d2 >= 0.0 {
+        clipped.push(v2);
+    }
+    if d1 * d2 < 0.0 {
+        clipped.push(v1.add(&v2.sub(&v1).mul(d1 / (d1 - d2))));
+    }
+    return clipped;
+}
+
+// Clips the incident edge against the reference edge to get up to two contact points
+fn contact_points(a: &[Vector], b: &[Vector], normal: &Vector) -> Vec<Vector> {
+    let edge_a = best_edge(a, normal);
+    let edge_b = best_edge(b, &normal.mul(-1.0));
+
+    // The reference edge is the one most perpendicular to the collision normal
+    let dir_a = edge_a.end.sub(&edge_a.start).norm();
+    let dir_b = edge_b.end.sub(&edge_b.start).norm();
+    let (reference, incident, ref_normal) = if dir_a.dot(normal).abs() <= dir_b.dot(normal).abs() {
+        (edge_a, edge_b, *normal)
+    } else {
+        (edge_b, edge_a, normal.mul(-1.0))
+    };
+
+    let ref_dir = reference.end.sub(&reference.start).norm();
+    let o1 = ref_dir.dot(&reference.start);
+    let clipped = clip(incident.start, incident.end, &ref_dir, o1);
+    if clipped.len() < 2 {
+        return vec![incident.max];
+    }
+    let o2 = ref_dir.dot(&reference.end);
+    let clipped = clip(clipped[0], clipped[1], &ref_dir.mul(-1.0), -o2);
+    if clipped.len() < 2 {
+        return vec![incident.max];
+    }
+
+    // Only keep points that are actually behind the reference face
+    let mut face_normal = Vector{x: -ref_dir.y, y: ref_dir.x};
+    if face_normal.dot(&ref_normal) < 0.0 {
+        face_normal = face_normal.mul(-1.0);
+    }
+    let face_offset = face_normal.dot(&reference.max);
+    let points: Vec<Vector> = clipped.into_iter()
+        .filter(|p| face_normal.dot(p) <= face_offset)
+        .collect();
+    if points.is_empty() {
+        return vec![incident.max];
+    }
+    return points;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    // Axis aligned square, counterclockwise
+    fn square(x: f32, y: f32, half: f32) -> Vec<Vector> {
+        return vec![
+            Vector{x: x - half, y: y - half},
+            Vector{x: x + half, y: y - half},
+            Vector{x: x + half, y: y + half},
+            Vector{x: x - half, y: y + half},
+        ];
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        return (a - b).abs() < EPSILON;
+    }
+
+    #[test]
+    fn separated_polygons_dont_collide() {
+        assert!(sat_collision(&square(0.0, 0.0, 1.0), &square(3.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn touching_polygons_dont_collide() {
+        assert!(sat_collision(&square(0.0, 0.0, 1.0), &square(2.0, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn overlap_gives_normal_from_first_to_second_and_depth() {
+        let contact = sat_collision(&square(0.0, 0.0, 1.0), &square(1.5, 0.2, 1.0)).unwrap();
+        assert!(close(contact.normal.x, 1.0) && close(contact.normal.y, 0.0));
+        assert!(close(contact.depth, 0.5));
+
+        let contact = sat_collision(&square(1.5, 0.2, 1.0), &square(0.0, 0.0, 1.0)).unwrap();
+        assert!(close(contact.normal.x, -1.0) && close(contact.normal.y, 0.0));
+        assert!(close(contact.depth, 0.5));
+    }
+
+    #[test]
+    fn contained_polygon_is_pushed_out_the_nearest_side() {
+        let contact = sat_collision(&square(0.0, 0.0, 10.0), &square(8.0, 0.0, 1.0)).unwrap();
+        assert!(close(contact.normal.x, 1.0) && close(contact.normal.y, 0.0));
+        assert!(close(contact.depth, 3.0));
+    }
+
+    #[test]
+    fn face_against_face_gives_two_contact_points() {
+        // Overlapping in y from -0.8 to 1.0, along the face x = 1
+        let contact = sat_collision(&square(0.0, 0.0, 1.0), &square(1.9, 0.2, 1.0)).unwrap();
+        assert_eq!(contact.points.len(), 2);
+        let mut ys: Vec<f32> = contact.points.iter().map(|p| p.y).collect();
+        ys.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(close(ys[0], -0.8) && close(ys[1], 1.0));
+        for p in &contact.points {
+            assert!(p.x >= 0.9 - EPSILON && p.x <= 1.0 + EPSILON);
+        }
+    }
+
+    #[test]
+    fn corner_into_face_gives_the_corner() {
+        // Diamond with its left corner poking 0.1 into the square's right face
+        let diamond = vec![
+            Vector{x: 0.9, y: 0.0},
+            Vector{x: 1.9, y: -1.0},
+            Vector{x: 2.9, y: 0.0},
+            Vector{x: 1.9, y: 1.0},
+        ];
+        let contact = sat_collision(&square(0.0, 0.0, 1.0), &diamond).unwrap();
+        assert!(close(contact.depth, 0.1));
+        assert_eq!(contact.points.len(), 1);
+        assert!(close(contact.points[0].x, 0.9) && close(contact.points[0].y, 0.0));
+    }
+
+    #[test]
+    fn time_of_impact_is_fraction_of_motion_to_first_touch() {
+        let t = time_of_impact(&square(0.0, 0.0, 1.0), &square(5.0, 0.0, 1.0), &Vector{x: 6.0, y: 0.0}).unwrap();
+        assert!(close(t, 0.5));
+    }
+
+    #[test]
+    fn time_of_impact_through_a_thin_obstacle() {
+        // Would jump clean over the obstacle in one discrete step
+        let wall = vec![
+            Vector{x: 4.9, y: -5.0},
+            Vector{x: 5.1, y: -5.0},
+            Vector{x: 5.1, y: 5.0},
+            Vector{x: 4.9, y: 5.0},
+        ];
+        let t = time_of_impact(&square(0.0, 0.0, 1.0), &wall, &Vector{x: 20.0, y: 0.0}).unwrap();
+        assert!(close(t, 3.9 / 20.0));
+    }
+
+    #[test]
+    fn no_impact_when_falling_short_moving_away_or_passing_by() {
+        let a = square(0.0, 0.0, 1.0);
+        let b = square(5.0, 0.0, 1.0);
+        assert!(time_of_impact(&a, &b, &Vector{x: 2.0, y: 0.0}).is_none());
+        assert!(time_of_impact(&a, &b, &Vector{x: -6.0, y: 0.0}).is_none());
+        assert!(time_of_impact(&a, &square(5.0, 3.0, 1.0), &Vector{x: 10.0, y: 0.0}).is_none());
+    }
+
+    #[test]
+    fn no_impact_for_polygons_already_overlapping() {
+        assert!(time_of_impact(&square(0.0, 0.0, 1.0), &square(1.5, 0.0, 1.0), &Vector{x: 1.0, y: 0.0}).is_none());
+    }
+}
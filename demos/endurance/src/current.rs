@@ -0,0 +1,129 @@
+use crate::vector::Vector;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+use std::fs;
+
+// A circular eddy. Water turns around the center, fastest near it and fading out with distance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Gyre {
+    pub center: Vector,
+    pub radius: f32,
+    // Speed scale, positive turns clockwise on screen
+    pub strength: f32,
+}
+
+// Water velocity sampled on a regular grid, e.g. from ocean model output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrentGrid {
+    // World position of the first sample
+    pub origin: Vector,
+    pub cell_size: f32,
+    pub cols: usize,
+    pub rows: usize,
+    // Row major, cols * rows samples
+    pub velocities: Vec<Vector>,
+}
+
+// Water velocity everywhere in the world, in the same units as body velocities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CurrentField {
+    Still,
+    Gyres{drift: Vector, gyres: Vec<Gyre>},
+    Grid(CurrentGrid),
+}
+
+impl CurrentField {
+    // A handful of gyres scattered over the given area, on top of a gentle overall drift
+    pub fn random_gyres<R: Rng>(min: &Vector, max: &Vector, num_gyres: u32, max_speed: f32, rng: &mut R) -> CurrentField {
+        let drift = Vector{x: rng.gen_range(-1.0, 1.0), y: rng.gen_range(-1.0, 1.0)}.mul(max_speed * 0.2);
+        let mut gyres = Vec::new();
+        for _ in 0..num_gyres {
+            let center = Vector{x: rng.gen_range(min.x, max.x), y: rng.gen_range(min.y, max.y)};
+            let radius = rng.gen_range(150.0, 600.0);
+            let direction = if rng.gen::<bool>() { 1.0 } else { -1.0 };
+            let strength = direction * rng.gen_range(0.3, 1.0) * max_speed;
+            gyres.push(Gyre{center, radius, strength});
+        }
+        return CurrentField::Gyres{drift, gyres};
+    }
+
+    // Loads a grid from a text file:
+    //   <cols> <rows> <cell size> <origin x> <origin y>
+    // followed by one line per row of "vx vy" pairs, cols pairs per line.
+    pub fn load_grid(path: &str) -> Result<CurrentField, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Couldn't read current grid {}: {}", path, e))?;
+        let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+
+        let header = parse_numbers(lines.next().unwrap_or(""), path)?;
+        if header.len() != 5 {
+            return Err(format!("{}: header should be <cols> <rows> <cell size> <origin x> <origin y>", path));
+        }
+        let cols = header[0] as usize;
+        let rows = header[1] as usize;
+        if cols == 0 || rows == 0 || header[2] <= 0.0 {
+            return Err(format!("{}: grid needs a positive size", path));
+        }
+
+        let mut velocities = Vec::new();
+        for row in 0..rows {
+            let values = parse_numbers(lines.next().unwrap_or(""), path)?;
+            if values.len() != cols * 2 {
+                return Err(format!("{}: row {} has {} values, expected {}", path, row, values.len(), cols * 2));
+            }
+            for pair in values.chunks(2) {
+                velocities.push(Vector{x: pair[0], y: pair[1]});
+            }
+        }
+
+        let origin = Vector{x: header[3], y: header[4]};
+        return Ok(CurrentField::Grid(CurrentGrid{origin, cell_size: header[2], cols, rows, velocities}));
+    }
+
+    pub fn velocity_at(&self, position: &Vector) -> Vector {
+        return match self {
+            CurrentField::Still => Vector{x: 0.0, y: 0.0},
+            CurrentField::Gyres{drift, gyres} => {
+                gyres.iter().fold(*drift, |velocity, gyre| velocity.add(&gyre.velocity_at(position)))
+            },
+            CurrentField::Grid(grid) => grid.velocity_at(position),
+        };
+    }
+}
+
+impl Gyre {
+    // Gaussian vortex - divergence free, so ice circulates rather than collecting at the center
+    fn velocity_at(&self, position: &Vector) -> Vector {
+        let r = position.sub(&self.center);
+        let falloff = (-r.dot(&r) / (self.radius * self.radius)).exp();
+        let tangent = Vector{x: -r.y, y: r.x}.mul(1.0 / self.radius);
+        return tangent.mul(self.strength * falloff);
+    }
+}
+
+impl CurrentGrid {
+    fn sample(&self, col: usize, row: usize) -> Vector {
+        return self.velocities[row * self.cols + col];
+    }
+
+    // Bilinear between the surrounding samples, clamped to the edge of the grid
+    fn velocity_at(&self, position: &Vector) -> Vector {
+        let gx = ((position.x - self.origin.x) / self.cell_size).max(0.0).min((self.cols - 1) as f32);
+        let gy = ((position.y - self.origin.y) / self.cell_size).max(0.0).min((self.rows - 1) as f32);
+        let col = gx.floor() as usize;
+        let row = gy.floor() as usize;
+        let next_col = (col + 1).min(self.cols - 1);
+        let next_row = (row + 1).min(self.rows - 1);
+        let tx = gx - col as f32;
+        let ty = gy - row as f32;
+
+        let top = self.sample(col, row).mul(1.0 - tx).add(&self.sample(next_col, row).mul(tx));
+        let bottom = self.sample(col, next_row).mul(1.0 - tx).add(&self.sample(next_col, next_row).mul(tx));
+        return top.mul(1.0 - ty).add(&bottom.mul(ty));
+    }
+}
+
+fn parse_numbers(line: &str, path: &str) -> Result<Vec<f32>, String> {
+    return line.split_whitespace()
+        .map(|v| v.parse().map_err(|_| format!("{}: bad number {:?}", path, v)))
+        .collect();
+}
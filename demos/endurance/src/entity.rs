@@ -0,0 +1,29 @@
+use serde::{Serialize, Deserialize};
+
+// Stable handle for a body in the world. Indices into World's vectors shift as bergs come
+// and go, these don't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EntityId(pub u32);
+
+impl EntityId {
+    // Placeholder for bodies that haven't been added to a world yet
+    pub const UNASSIGNED: EntityId = EntityId(0);
+}
+
+// Hands out ids in order, starting after UNASSIGNED
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdAllocator {
+    next: u32,
+}
+
+impl IdAllocator {
+    pub fn new() -> IdAllocator {
+        return IdAllocator{next: 1};
+    }
+
+    pub fn allocate(&mut self) -> EntityId {
+        let id = EntityId(self.next);
+        self.next += 1;
+        return id;
+    }
+}
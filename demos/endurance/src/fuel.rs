@@ -0,0 +1,12 @@
+use crate::vector::Vector;
+use crate::entity::EntityId;
+use serde::{Serialize, Deserialize};
+
+// A depot of coal the boat can pick up by sailing over it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuelCache {
+    pub id: EntityId,
+    pub position: Vector,
+    // Tons of coal, in the same units as Boat::fuel
+    pub amount: f32,
+}
@@ -1,71 +1,35 @@
 use crate::vector::Vector;
 
-pub fn reflect(subject_pos: Vector, subject_dir: Vector, object_pos: Vector, object_dir: Vector ) -> Vector {
-    let n = subject_pos.sub(&object_pos).norm();
-    let a1 = subject_dir.dot(&n);
-    let a2 = object_dir.dot(&n);
-    let optimized_p = (2.0 * (a1 - a2)) / 2.0;
-    let new_direction = subject_dir.sub(&n.mul(optimized_p).mul(1.0));  // TODO: magic number
-    return new_direction;
-}
-
-// Implementation adapted from:
-// https://www.geeksforgeeks.org/check-if-two-given-line-segments-intersect/
-// TODO: Return enum not int
-pub fn orientation(p: Vector, q: Vector, r: Vector) -> i32 {
-
-    let val = (q.y - p.y) * (r.x - q.x) - (q.x- p.x) * (r.y - q.y);
-    if val == 0.0 {
-        return 0;
-    }
-    if val > 0.0 {
-        return 1;
-    }
-    return 2;
+pub fn euc_distance(p1: &Vector, p2: &Vector) -> f32 {
+    (((p1.x - p2.x).powf(2.0) + (p1.y - p2.y).powf(2.0)) as f32).sqrt()
 }
 
-pub fn on_segment(p: Vector, q: Vector, r: Vector) -> bool {
-    if q.x <= f32::max(p.x, r.y) && q.x >= f32::min(p.x, r.x) && q.y <= f32::max(p.y, r.y) && q.y >= f32::min(p.y, r.y) {
-        return true;
+// Shoelace formula, points in either winding order
+pub fn polygon_area(points: &[Vector]) -> f32 {
+    let mut twice_area = 0.0;
+    for i in 0..points.len() {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % points.len()];
+        twice_area += p1.x * p2.y - p2.x * p1.y;
     }
-    return false;
+    return (twice_area / 2.0).abs();
 }
 
-pub fn lines_intersect(p1: Vector, q1: Vector, p2: Vector, q2: Vector) -> bool {
-
-    let o1 = orientation(p1, q1, p2);
-    let o2 = orientation(p1, q1, q2);
-    let o3 = orientation(p2, q2, p1);
-    let o4 = orientation(p2, q2, q1);
-
-    // General case
-    if o1 != o2 && o3 != o4 {
-        return true;
+// Moment of inertia of a uniform polygon of the given mass about the local origin
+pub fn polygon_inertia(points: &[Vector], mass: f32) -> f32 {
+    let mut numerator = 0.0;
+    let mut denominator = 0.0;
+    for i in 0..points.len() {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % points.len()];
+        let cross = (p1.x * p2.y - p2.x * p1.y).abs();
+        numerator += cross * (p1.dot(&p1) + p1.dot(&p2) + p2.dot(&p2));
+        denominator += cross;
     }
-    if o1 == 0 && on_segment(p1, p2, q1) {
-        return true;
-    }
-
-    // p1, q1 and q2 are colinear and q2 lies on segment p1q1
-    if o2 == 0 && on_segment(p1, q2, q1) {
-        return true;
+    if denominator == 0.0 {
+        return 0.0;
     }
-
-    // p2, q2 and p1 are colinear and p1 lies on segment p2q2
-    if o3 == 0 && on_segment(p2, p1, q2) {
-        return true;
-    }
-
-    // p2, q2 and q1 are colinear and q1 lies on segment p2q2
-    if o4 == 0 && on_segment(p2, q1, q2) {
-        return true;
-    }
-
-    return false;
-}
-
-pub fn euc_distance(p1: &Vector, p2: &Vector) -> f32 {
-    (((p1.x - p2.x).powf(2.0) + (p1.y - p2.y).powf(2.0)) as f32).sqrt()
+    return mass * numerator / (6.0 * denominator);
 }
 
 
@@ -89,3 +53,162 @@ fn reflect_line(subject_pos: Vector, subject_dir: Vector, object_p1: Vector, obj
     return Vector{x:0.0, y:0.0};
 }
 */
+
+// Area weighted center of a polygon
+pub fn polygon_centroid(points: &[Vector]) -> Vector {
+    let mut twice_area = 0.0;
+    let mut center = Vector{x: 0.0, y: 0.0};
+    for i in 0..points.len() {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % points.len()];
+        let cross = p1.x * p2.y - p2.x * p1.y;
+        twice_area += cross;
+        center = center.add(&p1.add(&p2).mul(cross));
+    }
+    if twice_area == 0.0 {
+        // Degenerate, fall back to the vertex average
+        let sum = points.iter().fold(Vector{x: 0.0, y: 0.0}, |acc, p| acc.add(p));
+        return sum.mul(1.0 / points.len() as f32);
+    }
+    return center.mul(1.0 / (3.0 * twice_area));
+}
+
+// Cuts a convex polygon along the line through point in direction. Returns the pieces on
+// either side, each still convex and in the same winding order. A side is empty if the
+// line misses the polygon.
+pub fn split_polygon(points: &[Vector], point: &Vector, direction: &Vector) -> (Vec<Vector>, Vec<Vector>) {
+    let side = |p: &Vector| direction.x * (p.y - point.y) - direction.y * (p.x - point.x);
+    let mut left = Vec::new();
+    let mut right = Vec::new();
+    for i in 0..points.len() {
+        let p1 = points[i];
+        let p2 = points[(i + 1) % points.len()];
+        let s1 = side(&p1);
+        let s2 = side(&p2);
+        if s1 <= 0.0 {
+            left.push(p1);
+        }
+        if s1 >= 0.0 {
+            right.push(p1);
+        }
+        // Edge crosses the line, both pieces get the crossing point
+        if (s1 < 0.0 && s2 > 0.0) || (s1 > 0.0 && s2 < 0.0) {
+            let crossing = p1.add(&p2.sub(&p1).mul(s1 / (s1 - s2)));
+            left.push(crossing);
+            right.push(crossing);
+        }
+    }
+    return (left, right);
+}
+
+// Smallest convex polygon containing all the points (Andrew's monotone chain). Collinear points
+// are dropped.
+pub fn convex_hull(points: &[Vector]) -> Vec<Vector> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap().then(a.y.partial_cmp(&b.y).unwrap()));
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    // Lower chain left to right, then upper chain back. Each chain ends where the other starts.
+    let mut hull = half_hull(sorted.iter());
+    hull.pop();
+    let mut upper = half_hull(sorted.iter().rev());
+    upper.pop();
+    hull.append(&mut upper);
+    return hull;
+}
+
+// One side of the hull, keeping only points where the chain turns the same way
+fn half_hull<'a, I: Iterator<Item = &'a Vector>>(points: I) -> Vec<Vector> {
+    let turn = |o: &Vector, a: &Vector, b: &Vector| (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x);
+    let mut chain: Vec<Vector> = Vec::new();
+    for p in points {
+        while chain.len() >= 2 && turn(&chain[chain.len() - 2], &chain[chain.len() - 1], p) <= 0.0 {
+            chain.pop();
+        }
+        chain.push(*p);
+    }
+    return chain;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-4;
+
+    fn square(half: f32) -> Vec<Vector> {
+        return vec![
+            Vector{x: -half, y: -half},
+            Vector{x: half, y: -half},
+            Vector{x: half, y: half},
+            Vector{x: -half, y: half},
+        ];
+    }
+
+    #[test]
+    fn split_through_middle_gives_two_halves() {
+        let (left, right) = split_polygon(&square(1.0), &Vector{x: 0.0, y: 0.0}, &Vector{x: 0.0, y: 1.0});
+        assert_eq!(left.len(), 4);
+        assert_eq!(right.len(), 4);
+        assert!((polygon_area(&left) - 2.0).abs() < EPSILON);
+        assert!((polygon_area(&right) - 2.0).abs() < EPSILON);
+        // Pieces are on opposite sides of the line
+        assert!(left.iter().all(|p| p.x >= -EPSILON) || left.iter().all(|p| p.x <= EPSILON));
+        assert!(polygon_centroid(&left).x * polygon_centroid(&right).x < 0.0);
+    }
+
+    #[test]
+    fn split_off_center_keeps_total_area() {
+        let (left, right) = split_polygon(&square(1.0), &Vector{x: 0.5, y: 0.0}, &Vector{x: 1.0, y: 2.0});
+        assert!((polygon_area(&left) + polygon_area(&right) - 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn split_through_corners_shares_the_corners() {
+        let (left, right) = split_polygon(&square(1.0), &Vector{x: 0.0, y: 0.0}, &Vector{x: 1.0, y: 1.0});
+        assert_eq!(left.len(), 3);
+        assert_eq!(right.len(), 3);
+        assert!((polygon_area(&left) - 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn split_missing_the_polygon_leaves_one_side_empty() {
+        let (left, right) = split_polygon(&square(1.0), &Vector{x: 5.0, y: 0.0}, &Vector{x: 0.0, y: 1.0});
+        assert!(left.is_empty() != right.is_empty());
+        assert_eq!(left.len().max(right.len()), 4);
+    }
+
+    #[test]
+    fn hull_drops_interior_and_collinear_points() {
+        let mut points = square(1.0);
+        points.push(Vector{x: 0.0, y: 0.0});
+        points.push(Vector{x: 0.3, y: -0.2});
+        points.push(Vector{x: 0.0, y: -1.0});
+        points.push(Vector{x: 1.0, y: 0.5});
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        for corner in square(1.0) {
+            assert!(hull.contains(&corner));
+        }
+        assert!((polygon_area(&hull) - 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hull_ignores_duplicates_and_input_order() {
+        let mut points = square(1.0);
+        points.reverse();
+        points.extend(square(1.0));
+        let hull = convex_hull(&points);
+        assert_eq!(hull.len(), 4);
+        assert!((polygon_area(&hull) - 4.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn hull_of_fewer_than_three_points_is_the_points() {
+        assert!(convex_hull(&Vec::new()).is_empty());
+        let points = vec![Vector{x: 1.0, y: 0.0}, Vector{x: 0.0, y: 0.0}];
+        assert_eq!(convex_hull(&points), vec![Vector{x: 0.0, y: 0.0}, Vector{x: 1.0, y: 0.0}]);
+    }
+}
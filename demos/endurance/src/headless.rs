@@ -0,0 +1,109 @@
+use crate::world::World;
+use crate::keyboard_state::KeyboardState;
+use crate::{SIM_DT, PACK_PRESSURE_LIMIT, create_world};
+use crate::snapshot;
+use crate::entity::EntityId;
+use crate::cli::Options;
+use crate::replay::Replay;
+use std::time::Instant;
+use std::collections::{HashMap, HashSet};
+
+// Canned input for headless runs: full ahead the whole time, with alternating turns
+// so the boat actually ploughs through some ice
+fn scripted_input(tick: u64) -> KeyboardState {
+    let phase = (tick / 100) % 4;
+    return KeyboardState{w: true, a: phase == 1, d: phase == 3, ..Default::default()};
+}
+
+// Steps the simulation a fixed number of ticks without SDL or OpenGL, then prints a summary.
+// With a replay, its seed and input are used and it runs for exactly the length of the replay.
+pub fn run(options: &Options, playback: Option<Replay>) -> Result<(), String> {
+    let num_ticks = playback.as_ref().map_or(options.ticks, |replay| replay.len() as u32);
+
+    let mut world = create_world(options, &playback)?;
+    let mut recording = Replay::new(world.get_seed());
+
+    // Ticks spent in contact, per berg
+    let mut contact_ticks: HashMap<EntityId, u32> = HashMap::new();
+    let mut deepest_penetration: f32 = 0.0;
+    let mut peak_pressure: f32 = 0.0;
+
+    let start = Instant::now();
+    for tick in 0..num_ticks {
+        if world.is_game_over() {
+            println!("Game over: the ship sank at tick {}", world.get_stats().ticks);
+            break;
+        }
+        // Script by world tick, so a run continued from a snapshot carries on where it left off
+        let input = match &playback {
+            Some(replay) => replay.inputs[tick as usize],
+            None => scripted_input(world.get_stats().ticks),
+        };
+        recording.record(&input);
+        world.tick(&input, SIM_DT);
+        // A berg can touch several others in one tick, but that's still only one tick in contact
+        let mut touching = HashSet::new();
+        for collision in world.get_collisions() {
+            touching.insert(collision.a);
+            touching.insert(collision.b);
+            deepest_penetration = deepest_penetration.max(collision.contact.depth);
+        }
+        for id in touching {
+            *contact_ticks.entry(id).or_insert(0) += 1;
+        }
+        peak_pressure = peak_pressure.max(world.get_boat().pressure);
+    }
+    let elapsed = start.elapsed().as_secs_f32();
+
+    print_summary(&world, elapsed, &contact_ticks, deepest_penetration, peak_pressure);
+
+    if let Some(path) = &options.record {
+        recording.save(path)?;
+        println!("Recorded {} ticks to {}", recording.len(), path);
+    }
+    if let Some(path) = &options.save {
+        snapshot::save(&world, path)?;
+        println!("Saved world to {}", path);
+    }
+    return Ok(());
+}
+
+fn print_summary(world: &World, elapsed: f32, contact_ticks: &HashMap<EntityId, u32>, deepest_penetration: f32, peak_pressure: f32) {
+    let stats = world.get_stats();
+    let boat = world.get_boat();
+    let ices = world.get_ices();
+    let mean_ice_speed = ices.iter()
+        .map(|ice| ice.direction.length())
+        .sum::<f32>() / ices.len().max(1) as f32;
+
+    println!("Seed:              {}", world.get_seed());
+    println!("Ticks:             {}", stats.ticks);
+    println!("Elapsed:           {:.3}s ({:.3}ms/tick)", elapsed, 1000.0 * elapsed / stats.ticks.max(1) as f32);
+    println!("Bergs:             {}", ices.len());
+    println!("Boat position:     ({:.1}, {:.1})", boat.position.x, boat.position.y);
+    println!("Boat heading:      {:.1} deg", boat.heading.to_degrees());
+    println!("Boat speed:        {:.3}", boat.direction.length());
+    println!("Fuel:              {:.1} tons ({} caches left)", boat.fuel, world.get_fuel_caches().len());
+    println!("Ice pressure:      {:.0} (peak {:.0}, limit {:.0}){}", boat.pressure, peak_pressure, PACK_PRESSURE_LIMIT, if boat.is_beset() { ", beset" } else { "" });
+    let integrity = &boat.integrity;
+    println!("Hull integrity:    bow {:.0}% / midships {:.0}% / stern {:.0}%", 100.0 * integrity.bow, 100.0 * integrity.midships, 100.0 * integrity.stern);
+    println!("Mean berg speed:   {:.3}", mean_ice_speed);
+    let wind = world.get_wind();
+    println!("Wind:              {:.2} towards {:.0} deg (gust {:+.2})", wind.velocity().length(), wind.direction.to_degrees(), wind.velocity().length() - wind.strength);
+    println!("Boat collisions:   {}", stats.boat_collisions);
+    println!("Berg collisions:   {}", stats.ice_collisions);
+    println!("Fractures:         {}", stats.fractures);
+    println!("Melted / frozen:   {} / {}", stats.melted, stats.frozen);
+    println!("Deepest overlap:   {:.2}", deepest_penetration);
+    println!("Checksum:          {:016x}", world.checksum());
+
+    // Ties broken by id so the output is stable
+    let busiest = contact_ticks.iter()
+        .filter(|(id, _)| **id != boat.id)
+        .max_by_key(|(id, ticks)| (**ticks, std::cmp::Reverse(**id)));
+    if let Some((id, ticks)) = busiest {
+        if let Some(ice) = world.get_ice(*id) {
+            println!("Busiest berg:      #{} ({} contact ticks, now at ({:.1}, {:.1}))", id.0, ticks, ice.position.x, ice.position.y);
+        }
+    }
+}
@@ -1,47 +1,68 @@
-use sdl2::pixels::Color;
-use sdl2::render::{WindowCanvas, TextureQuery};
-use sdl2::rect::{Rect, Point};
-use crate::{HUD_FONT_PATH, WIDTH, GRID_SIZE, HEIGHT};
-use crate::vector::Vector;
+use crate::text::TextRenderer;
+use crate::world::World;
+use crate::HUD_FONT_SIZE;
 
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const WARNING_COLOR: [f32; 4] = [1.0, 0.35, 0.2, 1.0];
+const SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+// Hull sections weaker than this show in the warning color
+const HULL_WARNING_LEVEL: f32 = 0.25;
+// Pixels from the window edge to the readouts, and from each line to its shadow
+const MARGIN: f32 = 15.0;
+const SHADOW_OFFSET: f32 = 2.0;
+
+// Readouts drawn over the world in screen space
 pub struct Hud {
+    text: TextRenderer,
 }
 
 impl Hud {
-    pub fn new() -> Hud {
-        return Hud{};
+    // Needs a current GL context
+    pub fn new() -> Result<Hud, String> {
+        return Ok(Hud{text: TextRenderer::new(HUD_FONT_SIZE)?});
     }
 
-    // Draw the grids used in collision detection
-    pub fn draw_collision_grid(&self, canvas: &mut WindowCanvas, offset: Vector) {
-        canvas.set_draw_color(Color::RGB(255, 0, 0));
-        for x in -100i32..100i32 {
-            let p1 = Point::new((GRID_SIZE as i32 * x) - offset.x as i32, 0);
-            let p2 = Point::new((GRID_SIZE as i32 * x) - offset.x as i32, HEIGHT as i32);
-            let _ = canvas.draw_line(p1, p2);
-        }
-        for y in -100i32..100i32 {
-            let p1 = Point::new(0, (GRID_SIZE as i32 * y) - offset.y as i32);
-            let p2 = Point::new(WIDTH as i32, (GRID_SIZE as i32 * y) - offset.y as i32);
-            let _ = canvas.draw_line(p1, p2);
+    // Window size is in screen pixels
+    pub fn draw(&mut self, world: &World, fps: f32, width: u32, height: u32) {
+        let boat = world.get_boat();
+        let wind = world.get_wind().velocity();
+        // Compass bearing the wind is blowing towards, 0 up the screen and clockwise from there
+        let bearing = wind.x.atan2(-wind.y).to_degrees().rem_euclid(360.0);
+        let integrity = &boat.integrity;
+
+        let mut lines = vec![
+            (format!("FPS: {}", fps.round() as u32), TEXT_COLOR),
+            (format!("Wind: {:.1} towards {:03}", wind.length(), bearing.round() as u32 % 360), TEXT_COLOR),
+            (format!("Hull: bow {:.0}%  midships {:.0}%  stern {:.0}%",
+                     integrity.bow * 100.0, integrity.midships * 100.0, integrity.stern * 100.0),
+             if integrity.bow.min(integrity.midships).min(integrity.stern) < HULL_WARNING_LEVEL { WARNING_COLOR } else { TEXT_COLOR }),
+            (format!("Coal: {:.1} t", boat.fuel),
+             if boat.engine_output() < 1.0 { WARNING_COLOR } else { TEXT_COLOR }),
+        ];
+        if boat.is_beset() {
+            lines.push((format!("Pressure: {:.0}  BESET", boat.pressure), WARNING_COLOR));
+        } else {
+            lines.push((format!("Pressure: {:.0}", boat.pressure), TEXT_COLOR));
         }
-    }
 
-    pub fn draw_fps(&self, canvas: &mut WindowCanvas, fps: f32) {
-        let to_draw = format!("FPS: {}", fps as u32);
-        let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string()).unwrap();
-        let mut font = ttf_context.load_font(HUD_FONT_PATH, 60).unwrap();
-        font.set_style(sdl2::ttf::FontStyle::BOLD);
+        let line_height = self.text.line_height();
+        for (i, (line, color)) in lines.iter().enumerate() {
+            self.shadowed(line, MARGIN, MARGIN + i as f32 * line_height, *color);
+        }
 
-        let surface = font.render(&to_draw)
-            .blended(Color::RGBA(0, 0, 0, 255)).map_err(|e| e.to_string()).unwrap();
+        if world.is_game_over() {
+            let message = format!("The ship has sunk after {} ticks", world.get_stats().ticks);
+            let x = (width as f32 - self.text.measure(&message)) / 2.0;
+            let y = (height as f32 - line_height) / 2.0;
+            self.shadowed(&message, x, y, WARNING_COLOR);
+        }
 
-        let texture_creator = canvas.texture_creator();
-        let texture = texture_creator.create_texture_from_surface(&surface)
-            .map_err(|e| e.to_string()).unwrap();
+        self.text.flush(width, height);
+    }
 
-        let TextureQuery { width, height, .. } = texture.query();
-        let target = Rect::new(15, 0, width, height);
-        canvas.copy(&texture, None, Some(target)).unwrap();
+    // Drop shadow so text stays readable over both ice and open water
+    fn shadowed(&mut self, line: &str, x: f32, y: f32, color: [f32; 4]) {
+        self.text.queue(line, x + SHADOW_OFFSET, y + SHADOW_OFFSET, SHADOW_COLOR);
+        self.text.queue(line, x, y, color);
     }
-}
\ No newline at end of file
+}
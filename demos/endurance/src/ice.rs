@@ -1,104 +1,165 @@
-use sdl2::render::{WindowCanvas};
 use rand::Rng;
-use sdl2::pixels::Color;
 use crate::vector::{Vector};
-use crate::{GRID_SIZE, HEIGHT, WIDTH, BERG_MIN_SIZE, BERG_MAX_SIZE, DEBUG_MODE};
-use sdl2::gfx::primitives::DrawRenderer;
+use crate::{ICE_DENSITY, ICE_DECEL_FACTOR, ICE_ANGULAR_DECEL_FACTOR};
+use crate::geometry::{polygon_area, polygon_inertia, polygon_centroid, split_polygon};
+use crate::physics::{Body, RigidBody, cross, point_velocity};
+use crate::entity::EntityId;
+use crate::shapes::{BergKind, generate_outline};
+use serde::{Serialize, Deserialize};
 
 // Represents a discrete piece of ice
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ice {
+    // Assigned by World when the berg is added
+    pub id: EntityId,
     pub direction: Vector,
     pub position: Vector,
     // Maximum radius of circle underlying iceberg
     pub size: u32,
+    // Derived from the polygon area; position is treated as the center of mass
+    pub mass: f32,
+    pub inertia: f32,
+    // Radians, clockwise on screen
+    pub rotation: f32,
+    pub angular_velocity: f32,
+    // State at the start of the current tick, for render interpolation
+    pub prev_position: Vector,
+    pub prev_rotation: f32,
+    // Outline rotated to the current rotation, relative to position
     pub perimeter: Vec<Vector>,
-    triangles: Vec<Vec<Vector>>
+    // Outline in local space
+    outline: Vec<Vector>,
 }
 
 impl Ice {
 
-    pub fn new(position: Vector, direction: Vector, size: u32) -> Ice {
-
-        let num_sides = 5;
-        let mut rng = rand::thread_rng();
-        let mut perimeter  =  Vec::new();
-        let mut triangles = Vec::new();
-        let base_angle = 360.0 / num_sides as f32;
-
-        for i in 0..num_sides {
-            let dist = rng.gen_range(size/2, size);
-            let angle = i as f32 * base_angle;
-            let angle_rad = angle as f64 * std::f64::consts::PI / 180 as f64;
-            let r_x = angle_rad.cos() * 0.0 - angle_rad.sin() * (dist as f64);
-            let r_y = angle_rad.sin() * 0.0 - angle_rad.cos() * (dist as f64);
-            perimeter.push(Vector{x: r_x as f32, y: r_y as f32 });
-
-            // triangles (in local space) to be used for rendering later
-            if i > 0 {
-                let p1 = Vector{x: 0.0, y: 0.0};
-                let p2 = perimeter.get(i-1).unwrap().clone();
-                let p3 = perimeter.get(i).unwrap().clone();
-                let triangle = vec![p1, p2, p3];
-                triangles.push(triangle);
-            }
-        }
-        // Last triangle
-        let p1 = Vector{x: 0.0, y: 0.0};
-        let p2 = perimeter.first().unwrap().clone();
-        let p3 = perimeter.last().unwrap().clone();
-        triangles.push(vec![p1, p2, p3]);
+    // Shape and kind are random, drawn from the caller's rng so worlds can be reproduced from a seed
+    pub fn new<R: Rng>(position: Vector, direction: Vector, size: u32, rng: &mut R) -> Ice {
+        let kind = BergKind::random(size, rng);
+        return Ice::with_kind(position, direction, size, kind, rng);
+    }
+
+    pub fn with_kind<R: Rng>(position: Vector, direction: Vector, size: u32, kind: BergKind, rng: &mut R) -> Ice {
+        let outline = generate_outline(&kind.params(), size, rng);
+        return Ice::from_outline(position, direction, size, outline);
+    }
 
-        Ice{direction, position, size, perimeter, triangles}
+    // Builds a berg from a convex outline given in local space around position
+    pub fn from_outline(position: Vector, direction: Vector, size: u32, outline: Vec<Vector>) -> Ice {
+        let mut ice = Ice{id: EntityId::UNASSIGNED, direction, position, size, mass: 0.0, inertia: 0.0, rotation: 0.0, angular_velocity: 0.0, prev_position: position, prev_rotation: 0.0, perimeter: vec![], outline: vec![]};
+        ice.set_outline(outline);
+        return ice;
     }
 
-    pub fn calc_grid(&self) -> (i32, i32) {
-        let mut grid_x = (self.position.x / GRID_SIZE as f32) as i32;
-        let mut grid_y = (self.position.y / GRID_SIZE as f32) as i32;
+    // Replaces the shape, recomputing everything derived from it
+    fn set_outline(&mut self, outline: Vec<Vector>) {
+        self.mass = polygon_area(&outline) * ICE_DENSITY;
+        self.inertia = polygon_inertia(&outline, self.mass);
+        self.outline = outline;
+        self.update_perimeter();
+    }
 
-        // Offset grid regions of negative positions due to integer division giving region 0
-        // when a number x i -1.0 < x < 1.0
-        // There's probably a more elegant way of doing this
-        if self.position.x < 0.0 {
-            grid_x -= 1;
-        }
-        if self.position.y < 0.0 {
-            grid_y -= 1;
+    // Distance from the center to the furthest point of the outline
+    pub fn radius(&self) -> f32 {
+        return self.outline.iter().map(|p| p.length()).fold(0.0, f32::max);
+    }
+
+    // Takes amount off the berg's radius all the way round, keeping its shape
+    pub fn melt(&mut self, amount: f32) {
+        let radius = self.radius();
+        // Nothing left to melt, and scaling would divide by zero
+        if radius <= 0.0 {
+            return;
         }
-        return (grid_x, grid_y);
-    }
-
-    pub fn get_vertices(&self, offset: &Vector) -> Vec<f32> {
-        let mut ret = Vec::new();
-        for trigon in &self.triangles {
-            for vertex in trigon {
-
-                // Offset-adjusted points (position relative to an origin in the upper left corner of the visible screen)
-                let pos_x = vertex.x + self.position.x - offset.x;
-                let mut pos_y = vertex.y + self.position.y - offset.y;
-                let pos_z = 0.0;
-
-                // NDC System has bottom left origin, so adjust our y value (top left origin) into that system
-                pos_y = HEIGHT as f32 - pos_y;
-
-                // Map these points into the normalized device coordinates space
-                let input_range = WIDTH as f32;
-                let output_range = 1.0 - -1.0;
-                let output_x = (pos_x - 0.0)*output_range / input_range + -1.0;
-
-                let input_range = HEIGHT as f32;
-                let output_range = 1.0 - -1.0;
-                let output_y = (pos_y - 0.0)*output_range / input_range + -1.0;
-                ret.push(output_x);
-                ret.push(output_y);
-                ret.push(pos_z);
-
-                // Colors
-                ret.push(0.878);
-                ret.push(0.882);
-                ret.push(0.901);
+        let remaining = (radius - amount).max(0.0);
+        let scale = remaining / radius;
+        let outline = self.outline.iter().map(|p| p.mul(scale)).collect();
+        self.set_outline(outline);
+        // size bounds the radius for the broad phase and wind, so it has to shrink with the outline
+        self.size = remaining.ceil() as u32;
+    }
+
+    // Splits the berg along a line through point (world space) in direction. The pieces keep the
+    // velocity the parent had at their centers, including spin, so momentum carries over.
+    pub fn fracture(&self, point: &Vector, direction: &Vector) -> Vec<Ice> {
+        let world_perimeter: Vec<Vector> = self.perimeter.iter().map(|p| p.add(&self.position)).collect();
+        let (left, right) = split_polygon(&world_perimeter, point, direction);
+        let parent = self.body();
+
+        let mut pieces = Vec::new();
+        for piece in [left, right] {
+            if piece.len() < 3 {
+                continue;
             }
+            let center = polygon_centroid(&piece);
+            let outline: Vec<Vector> = piece.iter().map(|p| p.sub(&center)).collect();
+            let size = outline.iter().map(|p| p.length()).fold(0.0, f32::max).ceil() as u32;
+            let mut ice = Ice::from_outline(center, point_velocity(&parent, &center), size, outline);
+            ice.angular_velocity = self.angular_velocity;
+            pieces.push(ice);
+        }
+        return pieces;
+    }
+
+    // Bergs stand roughly the same height out of the water, so the area the wind catches grows
+    // with their width while their mass grows with its square
+    pub fn sail_area(&self) -> f32 {
+        return self.size as f32;
+    }
+
+    pub fn save_state(&mut self) {
+        self.prev_position = self.position;
+        self.prev_rotation = self.rotation;
+    }
+
+    // Position and rotation alpha of the way from the previous tick to the current one
+    pub fn interpolated(&self, alpha: f32) -> (Vector, f32) {
+        let position = self.prev_position.add(&self.position.sub(&self.prev_position).mul(alpha));
+        let rotation = self.prev_rotation + (self.rotation - self.prev_rotation) * alpha;
+        return (position, rotation);
+    }
+
+    // Spins the berg, spinning down and dragged towards the velocity of the water around it.
+    // steps is the length of the update in tuning ticks (see TUNING_RATE). World moves the berg
+    // afterwards, so fast ones can be swept against their neighbours.
+    pub fn update(&mut self, steps: f32, water: &Vector) {
+        let relative = self.direction.sub(water);
+        self.direction = water.add(&relative.mul(ICE_DECEL_FACTOR.powf(steps)));
+        self.angular_velocity *= ICE_ANGULAR_DECEL_FACTOR.powf(steps);
+        if self.angular_velocity != 0.0 {
+            self.rotation += self.angular_velocity * steps;
+            self.update_perimeter();
         }
-        return ret;
+    }
+
+    fn update_perimeter(&mut self) {
+        let rotation = self.rotation;
+        self.perimeter = self.outline.iter().map(|p| p.rotate(rotation)).collect();
+    }
+
+    // Local space outline, unrotated
+    pub fn get_outline(&self) -> &Vec<Vector> {
+        return &self.outline;
+    }
+}
+
+impl RigidBody for Ice {
+    fn body(&self) -> Body {
+        return Body{
+            position: self.position,
+            velocity: self.direction,
+            angular_velocity: self.angular_velocity,
+            inv_mass: 1.0 / self.mass,
+            inv_inertia: 1.0 / self.inertia,
+        };
+    }
+
+    fn apply_impulse(&mut self, impulse: &Vector, contact: &Vector) {
+        self.direction = self.direction.add(&impulse.mul(1.0 / self.mass));
+        self.angular_velocity += cross(&contact.sub(&self.position), impulse) / self.inertia;
+    }
+
+    fn translate(&mut self, offset: &Vector) {
+        self.position = self.position.add(offset);
     }
 }
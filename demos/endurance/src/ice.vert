@@ -0,0 +1,38 @@
+#version 330 core
+
+// Drawn instanced, one instance per berg. Each berg's outline is stored once in Outlines, at
+// Outline.x for Outline.y points, and drawn as a fan of triangles from its center. Instances
+// with fewer sides than the largest berg collapse their spare triangles to a point.
+layout (location = 0) in vec2 InstancePosition;
+layout (location = 1) in float InstanceRotation;
+layout (location = 2) in vec3 InstanceColor;
+layout (location = 3) in ivec2 Outline;
+
+uniform samplerBuffer Outlines;
+// World space to normalized device coordinates, from Camera::view_projection
+uniform mat4 ViewProjection;
+
+out VS_OUTPUT {
+    vec3 Color;
+} OUT;
+
+void main()
+{
+    int triangle = gl_VertexID / 3;
+    int corner = gl_VertexID % 3;
+    vec2 local = vec2(0.0, 0.0);
+    if (triangle < Outline.y) {
+        if (corner == 1) {
+            local = texelFetch(Outlines, Outline.x + triangle).xy;
+        } else if (corner == 2) {
+            local = texelFetch(Outlines, Outline.x + (triangle + 1) % Outline.y).xy;
+        }
+    }
+
+    // Clockwise on screen, the same as Vector::rotate
+    float s = sin(InstanceRotation);
+    float c = cos(InstanceRotation);
+    vec2 world = InstancePosition + vec2(local.x * c - local.y * s, local.x * s + local.y * c);
+    gl_Position = ViewProjection * vec4(world, 0.0, 1.0);
+    OUT.Color = InstanceColor;
+}
@@ -1,17 +1,43 @@
 use sdl2::EventPump;
 use crate::keyboard_state::KeyboardState;
-use sdl2::event::Event;
+use crate::mouse_state::MouseState;
+use crate::ui_state::UiState;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
 
 pub struct InputManager {
     event_pump: EventPump,
-    keyboard_state: KeyboardState
+    keyboard_state: KeyboardState,
+    mouse_state: MouseState,
+    ui_state: UiState,
+    // Latest window size in screen pixels, if it's changed since take_resize was last called
+    resized: Option<(u32, u32)>
 }
 
 impl InputManager {
     pub fn new(event_pump: EventPump) -> InputManager {
-        let keyboard_state = KeyboardState{w:false, a:false, s:false, d:false, esc:false};
-        return InputManager{event_pump, keyboard_state};
+        let keyboard_state = KeyboardState::default();
+        let mouse_state = MouseState::default();
+        let ui_state = UiState::default();
+        return InputManager{event_pump, keyboard_state, mouse_state, ui_state, resized: None};
+    }
+
+    // Mouse as of the last get_keyboard_state, which is what polls events. The wheel counts
+    // notches since the previous call.
+    pub fn get_mouse_state(&mut self) -> MouseState {
+        let mouse_state = self.mouse_state;
+        self.mouse_state.wheel = 0;
+        return mouse_state;
+    }
+
+    // Display toggles as of the last get_keyboard_state
+    pub fn get_ui_state(&self) -> UiState {
+        return self.ui_state;
+    }
+
+    pub fn take_resize(&mut self) -> Option<(u32, u32)> {
+        return self.resized.take();
     }
 
     pub fn get_keyboard_state(&mut self) -> KeyboardState {
@@ -43,6 +69,15 @@ impl InputManager {
                     // println!("Key down esc");
                     self.keyboard_state.esc = true;
                 },
+                Event::KeyDown { keycode: Some(Keycode::C), repeat: false, .. } => {
+                    self.ui_state.c = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::F), repeat: false, .. } => {
+                    self.ui_state.f = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::G), repeat: false, .. } => {
+                    self.ui_state.g = true;
+                },
                 Event::KeyUp { keycode: Some(Keycode::W), repeat: false, .. } => {
                     self.keyboard_state.w = false;
                     // println!("Key up W");
@@ -64,10 +99,35 @@ impl InputManager {
                     // println!("Key down esc");
                     self.keyboard_state.esc = false;
                 },
-                /*
+                Event::KeyUp { keycode: Some(Keycode::C), repeat: false, .. } => {
+                    self.ui_state.c = false;
+                },
+                Event::KeyUp { keycode: Some(Keycode::F), repeat: false, .. } => {
+                    self.ui_state.f = false;
+                },
+                Event::KeyUp { keycode: Some(Keycode::G), repeat: false, .. } => {
+                    self.ui_state.g = false;
+                },
+                Event::Window { win_event: WindowEvent::SizeChanged(width, height), .. } => {
+                    self.resized = Some((width.max(0) as u32, height.max(0) as u32));
+                },
+                Event::MouseMotion { x, y, .. } => {
+                    self.mouse_state.x = x;
+                    self.mouse_state.y = y;
+                },
+                Event::MouseWheel { y, .. } => {
+                    self.mouse_state.wheel += y;
+                },
                 Event::MouseButtonDown { x, y, mouse_btn: MouseButton::Left, .. } => {
+                    self.mouse_state.x = x;
+                    self.mouse_state.y = y;
+                    self.mouse_state.left = true;
+                },
+                Event::MouseButtonUp { x, y, mouse_btn: MouseButton::Left, .. } => {
+                    self.mouse_state.x = x;
+                    self.mouse_state.y = y;
+                    self.mouse_state.left = false;
                 },
-                */
                 _ => {}
             }
         }
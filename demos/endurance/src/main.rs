@@ -1,14 +1,16 @@
 extern crate rand;
 
-use sdl2::pixels::Color;
-use sdl2::render::{WindowCanvas};
 use world::World;
 use std::{thread, time};
 use std::time::{Instant};
 use crate::input_manager::InputManager;
+use crate::cli::Options;
+use crate::replay::Replay;
+use crate::current::CurrentField;
+use crate::renderer::Renderer;
 use crate::hud::Hud;
-use sdl2::Sdl;
-use std::ffi::CString;
+use crate::camera::{Camera, CameraMode};
+use crate::vector::Vector;
 
 mod world;
 mod ice;
@@ -16,25 +18,189 @@ mod boat;
 mod geometry;
 mod vector;
 mod keyboard_state;
+mod mouse_state;
+mod ui_state;
 mod input_manager;
 mod hud;
+mod text;
+mod renderer;
+mod camera;
+mod headless;
+mod cli;
+mod physics;
+mod collision;
+mod spatial_hash;
+mod entity;
+mod current;
+mod wind;
+mod temperature;
+mod fuel;
+mod shapes;
+mod replay;
+mod snapshot;
 pub mod render_gl;
 
 
+// Size the window opens at, in screen pixels. It can be resized freely after that.
 pub const WIDTH: u32 = 800*2;
 pub const HEIGHT: u32 = 800*2;
-pub const FPS: u32 = 30;
+// Size of the generated world, which has nothing to do with the window
+pub const WORLD_WIDTH: u32 = 1600;
+pub const WORLD_HEIGHT: u32 = 1600;
+// Frames are paced by vsync. This cap only applies where vsync isn't available, and sits well
+// above the simulation rate so interpolated frames still get drawn between steps.
+pub const MAX_FPS: u32 = 240;
+// The simulation always advances in steps of this many seconds, whatever the frame rate
+pub const SIM_DT: f32 = 1.0 / 60.0;
+// Per-tick constants below (velocities, drag factors, turn rates) were tuned at this many ticks
+// per second. World::tick scales them by dt so they mean the same thing at any step size.
+pub const TUNING_RATE: f32 = 30.0;
+// Cap on real time fed to the simulation per frame, so a stall doesn't trigger a flood of steps
+pub const MAX_FRAME_TIME: f32 = 0.25;
 pub const BOAT_SIZE: u32 = 25;
 pub const NUM_BERGS: i32 = 1024 * 4;
 pub const BERG_MIN_SIZE: u32 = 8;
 pub const BERG_MAX_SIZE: u32 = 75;
+// Fraction of a body's velocity relative to the water that survives each tick
 pub const ICE_DECEL_FACTOR: f32 = 0.99;
+pub const ICE_ANGULAR_DECEL_FACTOR: f32 = 0.98;
+// Mass per unit of area
+pub const ICE_DENSITY: f32 = 1.0;
+// 1.0 is perfectly elastic, 0.0 means colliding bodies move off together
+pub const ICE_RESTITUTION: f32 = 0.5;
+// Overlap allowed between touching bodies before they're pushed apart, and the fraction of the
+// rest that's removed each tick
+pub const PENETRATION_SLOP: f32 = 0.5;
+pub const PENETRATION_CORRECTION: f32 = 0.6;
+pub const BOAT_MASS: f32 = 8000.0;
+// Impulse per unit of berg size the boat has to deliver to crack a berg. A second crack forms
+// above twice this.
+pub const ICE_HARDNESS: f32 = 60.0;
+// Random wobble on the direction of a crack, radians
+pub const FRACTURE_ANGLE_JITTER: f32 = 0.3;
+// Bodies moving less than this per tick can't skip past even the smallest berg, so only faster
+// ones get swept collision
+pub const CCD_MIN_TRAVEL: f32 = BERG_MIN_SIZE as f32 / 2.0;
+// How far a swept body carries on past its time of impact, so the contact registers
+pub const CCD_SKIN: f32 = 0.5;
+// Most times the boat's move can be cut short by a collision and carried on in one tick
+pub const BOAT_MAX_SUBSTEPS: u32 = 4;
+// Collision energy the hull shrugs off, and integrity lost per unit of energy above that
+pub const HULL_DAMAGE_THRESHOLD: f32 = 200.0;
+pub const HULL_DAMAGE_PER_ENERGY: f32 = 0.00001;
+// Fraction of top speed and acceleration left with the hull at zero integrity
+pub const DAMAGED_MIN_SPEED_FACTOR: f32 = 0.3;
+// Tons of coal the bunkers hold, and burnt per tick at full throttle
+pub const BOAT_FUEL_CAPACITY: f32 = 100.0;
+pub const FUEL_BURN_RATE: f32 = 0.01;
+// Below this much coal the engine can't hold full steam, and its output falls off to nothing
+pub const FUEL_LOW_LEVEL: f32 = 10.0;
+pub const NUM_FUEL_CACHES: u32 = 12;
+pub const FUEL_CACHE_AMOUNT: f32 = 25.0;
+// How close the boat has to come to a cache to load it
+pub const FUEL_CACHE_RADIUS: f32 = 20.0;
+// Pack ice pressure is the compressive force on the hull, averaged with this fraction of the
+// previous average kept each tick
+pub const PRESSURE_SMOOTHING: f32 = 0.95;
+// Pressure the ship can stand indefinitely
+pub const PACK_PRESSURE_LIMIT: f32 = 2000.0;
+// Ticks above the limit before the ship is beset (can't make way), and then starts to be crushed
+pub const BESET_TICKS: f32 = 60.0;
+pub const CRUSH_TICKS: f32 = 240.0;
+// Midships integrity lost per tick per unit of pressure above the limit
+pub const CRUSH_DAMAGE_RATE: f32 = 0.000001;
 pub const BOAT_ACCELERATION: f32 = 0.1;
-pub const HUD_FONT_PATH: &str = "/home/malcolm/Downloads/RobotoCondensed-Bold.ttf";
+pub const BOAT_REVERSE_THROTTLE: f32 = 0.5;
+pub const BOAT_MAX_SPEED: f32 = 6.0;
+// Radians per tick with full rudder at top speed
+pub const BOAT_TURN_RATE: f32 = 0.05;
+pub const BOAT_DRAG: f32 = 0.99;
+// The keel resists sideways motion much more than forward motion
+pub const BOAT_LATERAL_DRAG: f32 = 0.9;
+pub const NUM_GYRES: u32 = 6;
+pub const CURRENT_MAX_SPEED: f32 = 0.6;
+// Spacing (pixels) and length scale of the current overlay's arrows
+pub const CURRENT_ARROW_SPACING: u32 = 80;
+pub const CURRENT_ARROW_SCALE: f32 = 60.0;
+// Wind speeds are in the same units as body velocities
+pub const WIND_MAX_SPEED: f32 = 8.0;
+// Gusts add or take away up to this fraction of the prevailing wind
+pub const WIND_GUST_FACTOR: f32 = 0.6;
+// A new gust starts every this many ticks
+pub const WIND_GUST_MIN_TICKS: f32 = 30.0;
+pub const WIND_GUST_MAX_TICKS: f32 = 150.0;
+// Fraction of the way to the next gust covered each tick
+pub const WIND_GUST_EASE: f32 = 0.05;
+// Velocity change per tick per unit of exposed area over mass, per unit of wind
+pub const WIND_DRAG: f32 = 0.008;
+// Boat area exposed to the wind, in multiples of its size, bow-on and beam-on
+pub const BOAT_BOW_WINDAGE: f32 = 2.0;
+pub const BOAT_BEAM_WINDAGE: f32 = 5.0;
+// Sea water temperatures, degrees C. Ice melts above freezing and can form below it.
+pub const FREEZING_POINT: f32 = -1.8;
+pub const WATER_BASE_TEMPERATURE: f32 = -2.5;
+pub const NUM_WARM_PATCHES: u32 = 3;
+pub const WARM_PATCH_MAX_WARMTH: f32 = 4.0;
+// Radius lost per tick per degree above freezing
+pub const MELT_RATE: f32 = 0.0005;
+// Melting and freezing are slow, so they only run every this many ticks
+pub const THAW_INTERVAL: u64 = 30;
+// Random spots tried for new ice each interval, and the chance each calm, cold one freezes
+pub const FREEZE_ATTEMPTS: u32 = 4;
+pub const FREEZE_CHANCE: f32 = 0.5;
+// Water is too rough to freeze above these speeds
+pub const FREEZE_MAX_CURRENT: f32 = 0.2;
+pub const FREEZE_MAX_WIND: f32 = 5.0;
+// How quickly the camera catches up with the boat, per second, and how many ticks of the boat's
+// velocity it looks ahead
+pub const CAMERA_STIFFNESS: f32 = 4.0;
+pub const CAMERA_LOOK_AHEAD: f32 = 40.0;
+// Zoom limits, and the factor each notch of the mouse wheel zooms by
+pub const CAMERA_MIN_ZOOM: f32 = 0.25;
+pub const CAMERA_MAX_ZOOM: f32 = 4.0;
+pub const CAMERA_ZOOM_STEP: f32 = 1.1;
+// Point size of the HUD's text
+pub const HUD_FONT_SIZE: u16 = 28;
+// Fraction of the previous frame rate kept each frame, to steady the readout
+pub const FPS_SMOOTHING: f32 = 0.9;
+// Whether the debug overlay starts shown. G toggles it.
 pub const DEBUG_MODE: bool = false;
+// Ticks of motion the debug overlay's velocity lines cover, and length of its contact normals
+pub const DEBUG_VELOCITY_SCALE: f32 = 10.0;
+pub const DEBUG_NORMAL_LENGTH: f32 = 20.0;
 pub const GRID_SIZE: u32 = 2 * BERG_MAX_SIZE + 10;
 
 fn main() -> Result<(), String> {
+    let options = Options::parse()?;
+    let playback = match &options.replay {
+        Some(path) => Some(Replay::load(path)?),
+        None => None,
+    };
+    if options.headless {
+        return headless::run(&options, playback);
+    }
+    run_windowed(&options, playback)
+}
+
+// Either loads a snapshot or generates a fresh world. A replay brings its own seed.
+pub fn create_world(options: &Options, playback: &Option<Replay>) -> Result<World, String> {
+    if let Some(path) = &options.load {
+        let world = snapshot::load(path)?;
+        println!("Loaded {} (seed {}, tick {})", path, world.get_seed(), world.get_stats().ticks);
+        return Ok(world);
+    }
+    let seed = playback.as_ref().map_or(options.seed, |replay| replay.seed);
+    let mut world = World::new(WORLD_WIDTH, WORLD_HEIGHT, seed);
+    if let Some(path) = &options.currents {
+        world.set_current(CurrentField::load_grid(path)?);
+    }
+    // world.init_test();
+    world.init_with_random_ice(NUM_BERGS);
+    world.init_with_random_fuel(NUM_FUEL_CACHES);
+    return Ok(world);
+}
+
+fn run_windowed(options: &Options, playback: Option<Replay>) -> Result<(), String> {
     let sdl = sdl2::init().unwrap();
     let video_subsystem = sdl.video().unwrap();
 
@@ -53,30 +219,46 @@ fn main() -> Result<(), String> {
     let _gl_context = window.gl_create_context().unwrap();
     let _gl =
         gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+    if let Err(e) = video_subsystem.gl_set_swap_interval(1) {
+        println!("Couldn't enable vsync, capping at {} fps: {}", MAX_FPS, e);
+    }
 
-    let vert_shader =
-        render_gl::Shader::from_vert_source(&CString::new(include_str!("triangle.vert")).unwrap())
-            .unwrap();
-
-    let frag_shader =
-        render_gl::Shader::from_frag_source(&CString::new(include_str!("triangle.frag")).unwrap())
-            .unwrap();
-
-    let shader_program = render_gl::Program::from_shaders(&[vert_shader, frag_shader]).unwrap();
+    let mut renderer = Renderer::new()?;
 
     let event_pump = sdl.event_pump()?;
     let mut input_manager = InputManager::new(event_pump);
 
-    // TODO: Migrate hud to opengl
-    // let hud = Hud::new();
+    let mut hud = Hud::new()?;
 
-    let mut world = World::new(WIDTH, HEIGHT);
-    // world.init_test();
-    world.init_with_random_ice(NUM_BERGS);
+    let mut world = create_world(options, &playback)?;
+    let mut recording = Replay::new(world.get_seed());
+    // Headless runs print it in their summary, and loading a snapshot already reports it
+    if options.load.is_none() {
+        println!("Seed: {}", world.get_seed());
+    }
 
-    let frame_length = 1000.0 / FPS as f32;
+    let frame_length = time::Duration::from_secs_f32(1.0 / MAX_FPS as f32);
+    let mut previous_frame = Instant::now();
+    let mut accumulator = 0.0;
+    let mut show_current = false;
+    let mut c_was_down = false;
+    let mut f_was_down = false;
+    let mut show_debug = DEBUG_MODE;
+    let mut g_was_down = false;
+    let (window_width, window_height) = window.size();
+    let mut camera = Camera::new(world.get_boat().position, window_width, window_height);
+    set_viewport(&window);
+    let mut mouse_was = input_manager.get_mouse_state();
+    let mut sunk = world.is_game_over();
+    let mut fps = MAX_FPS as f32;
     'running: loop {
         let frame_start = Instant::now();
+        let frame_dt = frame_start.duration_since(previous_frame).as_secs_f32();
+        accumulator += frame_dt.min(MAX_FRAME_TIME);
+        if frame_dt > 0.0 {
+            fps = fps * FPS_SMOOTHING + (1.0 - FPS_SMOOTHING) / frame_dt;
+        }
+        previous_frame = frame_start;
         unsafe {
             gl::ClearColor(0.156, 0.298, 0.823, 1.0);
             gl::Clear(gl::COLOR_BUFFER_BIT);
@@ -86,16 +268,89 @@ fn main() -> Result<(), String> {
         if keyboard_state.esc {
             break 'running;
         }
+        if let Some((width, height)) = input_manager.take_resize() {
+            camera.set_viewport(width, height);
+            set_viewport(&window);
+        }
+        let ui_state = input_manager.get_ui_state();
+        if ui_state.c && !c_was_down {
+            show_current = !show_current;
+        }
+        c_was_down = ui_state.c;
+        if ui_state.f && !f_was_down {
+            camera.toggle_mode();
+        }
+        f_was_down = ui_state.f;
+        if ui_state.g && !g_was_down {
+            show_debug = !show_debug;
+        }
+        g_was_down = ui_state.g;
+
+        let mouse = input_manager.get_mouse_state();
+        let cursor = Vector{x: mouse.x as f32, y: mouse.y as f32};
+        if mouse.wheel != 0 {
+            camera.zoom_at(&cursor, mouse.wheel);
+        }
+        if camera.mode == CameraMode::FreePan && mouse.left && mouse_was.left {
+            camera.pan(&Vector{x: mouse_was.x as f32, y: mouse_was.y as f32}, &cursor);
+        }
+        mouse_was = mouse;
+
+        // Run however many fixed steps fit in the real time that has passed, and render the
+        // leftover fraction of a step by interpolating between the last two states
+        while accumulator >= SIM_DT {
+            // Replayed input takes over from the keyboard until it runs out
+            let tick = world.get_stats().ticks as usize;
+            let input = match &playback {
+                Some(replay) if tick < replay.len() => replay.inputs[tick],
+                _ => keyboard_state,
+            };
+            recording.record(&input);
+            world.tick(&input, SIM_DT);
+            accumulator -= SIM_DT;
+        }
+        let alpha = accumulator / SIM_DT;
+
+        if world.is_game_over() && !sunk {
+            sunk = true;
+            println!("Game over: the ship sank at tick {}", world.get_stats().ticks);
+        }
 
-        world.tick(&keyboard_state);
-        world.draw_gl(&shader_program);
+        let (boat_position, _) = world.get_boat().interpolated(alpha);
+        camera.follow(&boat_position, &world.get_boat().direction, frame_dt);
+        renderer.draw(&world, &camera, alpha);
+        if show_current {
+            renderer.draw_current(&world, &camera);
+        }
+        if show_debug {
+            renderer.draw_debug(&world, &camera);
+        }
+        let (window_width, window_height) = window.size();
+        hud.draw(&world, fps, window_width, window_height);
         window.gl_swap_window();
 
         let elapsed = frame_start.elapsed();
-        if elapsed.as_millis() < frame_length as u128 {
-            thread::sleep(time::Duration::from_millis((frame_length - elapsed.as_millis() as f32) as u64));
+        if elapsed < frame_length {
+            thread::sleep(frame_length - elapsed);
         }
-        println!("FPS: {:}", 1000.0 / frame_start.elapsed().as_millis() as f32)
+    }
+
+    if let Some(path) = &options.record {
+        recording.save(path)?;
+        println!("Recorded {} ticks to {}", recording.len(), path);
+    }
+    if let Some(path) = &options.save {
+        snapshot::save(&world, path)?;
+        println!("Saved world to {}", path);
     }
     Ok(())
 }
+
+// The GL viewport is in drawable pixels, which on high DPI displays can be more than the window's
+// screen pixels that the camera and mouse work in
+fn set_viewport(window: &sdl2::video::Window) {
+    let (width, height) = window.drawable_size();
+    unsafe {
+        gl::Viewport(0, 0, width as gl::types::GLsizei, height as gl::types::GLsizei);
+    }
+}
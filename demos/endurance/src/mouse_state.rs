@@ -0,0 +1,10 @@
+// Mouse input for the camera. Not part of the simulation input, so never recorded.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct MouseState {
+    // Pixels from the top left of the window
+    pub x: i32,
+    pub y: i32,
+    // Wheel notches since the last poll, positive away from the user
+    pub wheel: i32,
+    pub left: bool
+}
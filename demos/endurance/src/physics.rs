@@ -0,0 +1,74 @@
+use crate::vector::Vector;
+
+// Snapshot of the state the impulse solver needs from a body
+#[derive(Debug, Clone, Copy)]
+pub struct Body {
+    pub position: Vector,
+    pub velocity: Vector,
+    pub angular_velocity: f32,
+    // Zero inverse mass/inertia means the body can't be moved/spun by collisions
+    pub inv_mass: f32,
+    pub inv_inertia: f32,
+}
+
+pub trait RigidBody {
+    fn body(&self) -> Body;
+    // Apply an impulse at a world space contact point
+    fn apply_impulse(&mut self, impulse: &Vector, contact: &Vector);
+    // Move without changing velocity, for pushing overlapping bodies apart
+    fn translate(&mut self, offset: &Vector);
+}
+
+// 2D cross product, the z component of a x b
+pub fn cross(a: &Vector, b: &Vector) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+// Velocity of a point on a body, including the contribution from spin
+pub fn point_velocity(body: &Body, point: &Vector) -> Vector {
+    let r = point.sub(&body.position);
+    let spin = Vector{x: -body.angular_velocity * r.y, y: body.angular_velocity * r.x};
+    return body.velocity.add(&spin);
+}
+
+// Computes the impulse to apply to b (and the negation to a) at the contact point.
+// normal points from a towards b. Returns None if the bodies are already separating.
+pub fn collision_impulse(a: &Body, b: &Body, contact: &Vector, normal: &Vector, restitution: f32) -> Option<Vector> {
+    let relative_velocity = point_velocity(b, contact).sub(&point_velocity(a, contact));
+    let closing_speed = relative_velocity.dot(normal);
+    if closing_speed >= 0.0 {
+        return None;
+    }
+
+    let ra_n = cross(&contact.sub(&a.position), normal);
+    let rb_n = cross(&contact.sub(&b.position), normal);
+    let denominator = a.inv_mass + b.inv_mass + ra_n * ra_n * a.inv_inertia + rb_n * rb_n * b.inv_inertia;
+    if denominator <= 0.0 {
+        return None;
+    }
+
+    let j = -(1.0 + restitution) * closing_speed / denominator;
+    return Some(normal.mul(j));
+}
+
+// Equal and opposite impulses, so momentum is conserved. Returns the impulse applied to b, if any.
+pub fn resolve_collision<A: RigidBody, B: RigidBody>(a: &mut A, b: &mut B, contact: &Vector, normal: &Vector, restitution: f32) -> Option<Vector> {
+    let impulse = collision_impulse(&a.body(), &b.body(), contact, normal, restitution)?;
+    a.apply_impulse(&impulse.mul(-1.0), contact);
+    b.apply_impulse(&impulse, contact);
+    return Some(impulse);
+}
+
+// Pushes overlapping bodies apart along normal (pointing from a towards b), the lighter one moving
+// further. Only fraction of the overlap beyond slop is removed each time, so bodies resting against
+// each other settle instead of being knocked back and forth.
+pub fn separate<A: RigidBody, B: RigidBody>(a: &mut A, b: &mut B, normal: &Vector, depth: f32, slop: f32, fraction: f32) {
+    let (inv_a, inv_b) = (a.body().inv_mass, b.body().inv_mass);
+    let overlap = depth - slop;
+    if overlap <= 0.0 || inv_a + inv_b <= 0.0 {
+        return;
+    }
+    let correction = normal.mul(overlap * fraction / (inv_a + inv_b));
+    a.translate(&correction.mul(-inv_a));
+    b.translate(&correction.mul(inv_b));
+}
@@ -58,6 +58,13 @@ impl Program {
         self.id
     }
 
+    pub fn get_uniform_location(&self, name: &str) -> gl::types::GLint {
+        let name = CString::new(name).unwrap();
+        unsafe {
+            gl::GetUniformLocation(self.id, name.as_ptr())
+        }
+    }
+
     pub fn set_used(&self) {
         unsafe {
             gl::UseProgram(self.id);
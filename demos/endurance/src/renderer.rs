@@ -0,0 +1,405 @@
+use crate::world::World;
+use crate::render_gl::{Program, Shader};
+use crate::vector::Vector;
+use crate::camera::Camera;
+use crate::{CURRENT_ARROW_SPACING, CURRENT_ARROW_SCALE, FUEL_CACHE_RADIUS, DEBUG_VELOCITY_SCALE, DEBUG_NORMAL_LENGTH};
+use std::ffi::CString;
+
+// Everything that touches OpenGL for drawing the world lives here, so the simulation
+// itself can be stepped without a window or GL context
+
+const ICE_COLOR: [f32; 3] = [0.878, 0.882, 0.901];
+// Debug overlay colors
+const GRID_COLOR: [f32; 3] = [0.3, 0.4, 0.7];
+const OCCUPIED_CELL_COLOR: [f32; 3] = [0.9, 0.2, 0.2];
+const BOUNDS_COLOR: [f32; 3] = [0.4, 0.9, 0.4];
+const PERIMETER_COLOR: [f32; 3] = [1.0, 0.9, 0.1];
+const VELOCITY_COLOR: [f32; 3] = [1.0, 0.5, 0.0];
+const CONTACT_COLOR: [f32; 3] = [1.0, 0.0, 1.0];
+// Line segments making up each bounding circle
+const CIRCLE_SEGMENTS: usize = 24;
+// Half the width of the cross marking a contact point
+const CONTACT_MARKER_SIZE: f32 = 4.0;
+// Floats per berg in the instance buffer: position (2), rotation, color (3)
+const INSTANCE_FLOATS: usize = 6;
+
+// Owns the shaders and GL buffers, which live as long as the window rather than being made
+// every frame.
+//
+// Bergs are drawn instanced. Each berg's outline is uploaded once into a buffer texture, and only
+// re-uploaded when World reports that shapes have changed. Every frame, one streaming buffer of
+// per-berg position, rotation and color is filled and everything is drawn in a single call.
+// Everything else (the boat, caches, overlays) is a handful of vertices, built on the CPU and
+// streamed through one reused buffer.
+pub struct Renderer {
+    program: Program,
+    ice_program: Program,
+    // Plain xyz rgb vertices in world space
+    vao: gl::types::GLuint,
+    vbo: gl::types::GLuint,
+    ice_vao: gl::types::GLuint,
+    // Per berg position, rotation and color, refilled every frame
+    instance_vbo: gl::types::GLuint,
+    // Per berg start and length of its outline in the outline texture
+    outline_index_vbo: gl::types::GLuint,
+    // Every berg's local space outline, back to back
+    outline_buffer: gl::types::GLuint,
+    outline_texture: gl::types::GLuint,
+    // Most sides on any berg, which sets how many vertices each instance has
+    max_sides: i32,
+    // World::get_shape_generation the outlines were uploaded at
+    uploaded_generation: Option<u64>,
+}
+
+impl Renderer {
+    // Needs a current GL context
+    pub fn new() -> Result<Renderer, String> {
+        let program = Program::from_shaders(&[
+            Shader::from_vert_source(&CString::new(include_str!("triangle.vert")).unwrap())?,
+            Shader::from_frag_source(&CString::new(include_str!("triangle.frag")).unwrap())?,
+        ])?;
+        let ice_program = Program::from_shaders(&[
+            Shader::from_vert_source(&CString::new(include_str!("ice.vert")).unwrap())?,
+            Shader::from_frag_source(&CString::new(include_str!("triangle.frag")).unwrap())?,
+        ])?;
+
+        let mut renderer = Renderer{
+            program,
+            ice_program,
+            vao: 0,
+            vbo: 0,
+            ice_vao: 0,
+            instance_vbo: 0,
+            outline_index_vbo: 0,
+            outline_buffer: 0,
+            outline_texture: 0,
+            max_sides: 0,
+            uploaded_generation: None,
+        };
+
+        let float_size = std::mem::size_of::<f32>();
+        unsafe {
+            gl::GenVertexArrays(1, &mut renderer.vao);
+            gl::GenBuffers(1, &mut renderer.vbo);
+            gl::BindVertexArray(renderer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, renderer.vbo);
+            gl::EnableVertexAttribArray(0); // Position, "layout (location = 0)" in triangle.vert
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, (6 * float_size) as gl::types::GLint, std::ptr::null());
+            gl::EnableVertexAttribArray(1); // Color
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, (6 * float_size) as gl::types::GLint, (3 * float_size) as *const gl::types::GLvoid);
+
+            // Ice attributes all advance once per instance rather than per vertex
+            gl::GenVertexArrays(1, &mut renderer.ice_vao);
+            gl::GenBuffers(1, &mut renderer.instance_vbo);
+            gl::GenBuffers(1, &mut renderer.outline_index_vbo);
+            gl::BindVertexArray(renderer.ice_vao);
+            let stride = (INSTANCE_FLOATS * float_size) as gl::types::GLint;
+            gl::BindBuffer(gl::ARRAY_BUFFER, renderer.instance_vbo);
+            gl::EnableVertexAttribArray(0); // InstancePosition, in ice.vert
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::VertexAttribDivisor(0, 1);
+            gl::EnableVertexAttribArray(1); // InstanceRotation
+            gl::VertexAttribPointer(1, 1, gl::FLOAT, gl::FALSE, stride, (2 * float_size) as *const gl::types::GLvoid);
+            gl::VertexAttribDivisor(1, 1);
+            gl::EnableVertexAttribArray(2); // InstanceColor
+            gl::VertexAttribPointer(2, 3, gl::FLOAT, gl::FALSE, stride, (3 * float_size) as *const gl::types::GLvoid);
+            gl::VertexAttribDivisor(2, 1);
+            gl::BindBuffer(gl::ARRAY_BUFFER, renderer.outline_index_vbo);
+            gl::EnableVertexAttribArray(3); // Outline
+            gl::VertexAttribIPointer(3, 2, gl::INT, 0, std::ptr::null());
+            gl::VertexAttribDivisor(3, 1);
+
+            gl::GenBuffers(1, &mut renderer.outline_buffer);
+            gl::GenTextures(1, &mut renderer.outline_texture);
+            gl::BindBuffer(gl::TEXTURE_BUFFER, renderer.outline_buffer);
+            gl::BindTexture(gl::TEXTURE_BUFFER, renderer.outline_texture);
+            gl::TexBuffer(gl::TEXTURE_BUFFER, gl::RG32F, renderer.outline_buffer);
+
+            gl::BindTexture(gl::TEXTURE_BUFFER, 0);
+            gl::BindBuffer(gl::TEXTURE_BUFFER, 0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        return Ok(renderer);
+    }
+
+    // alpha is how far between the last two simulation states to draw, 0.0 - 1.0
+    pub fn draw(&mut self, world: &World, camera: &Camera, alpha: f32) {
+        let view_projection = camera.view_projection();
+        self.draw_ice(world, &view_projection, alpha);
+
+        // This is a vector of f32s, each group of six serially representing a vertex (xyz) and color info (rgb)
+        // Thus each group of eighteen (6*3) represents a triangle.
+        let mut vertices: Vec<f32> = Vec::new();
+
+        // Fuel caches are small dark diamonds
+        let coal = [0.1, 0.1, 0.1];
+        for cache in world.get_fuel_caches() {
+            let center = cache.position;
+            let corners = [
+                center.add(&Vector{x: 0.0, y: -FUEL_CACHE_RADIUS}),
+                center.add(&Vector{x: FUEL_CACHE_RADIUS, y: 0.0}),
+                center.add(&Vector{x: 0.0, y: FUEL_CACHE_RADIUS}),
+                center.add(&Vector{x: -FUEL_CACHE_RADIUS, y: 0.0}),
+            ];
+            for i in &[0, 1, 2, 0, 2, 3] {
+                push_vertex(&mut vertices, &corners[*i], &coal);
+            }
+        }
+
+        let mut boat_verts = world.get_boat().get_vertices(alpha);
+        vertices.append(&mut boat_verts);
+
+        self.draw_vertices(&vertices, &view_projection, gl::TRIANGLES);
+    }
+
+    // Debug overlay: an arrow showing the water velocity at each point of a world space grid
+    // over what's on screen
+    pub fn draw_current(&mut self, world: &World, camera: &Camera) {
+        let color = [0.6, 0.9, 1.0];
+        let spacing = CURRENT_ARROW_SPACING as f32;
+        let (min, max) = camera.visible_bounds();
+        let mut vertices: Vec<f32> = Vec::new();
+        // Snapped to the grid, so arrows stay put as the camera moves
+        let mut x = (min.x / spacing).floor() * spacing;
+        while x <= max.x {
+            let mut y = (min.y / spacing).floor() * spacing;
+            while y <= max.y {
+                let base = Vector{x, y};
+                let velocity = world.get_current().velocity_at(&base);
+                let tip = base.add(&velocity.mul(CURRENT_ARROW_SCALE));
+
+                // Shaft plus two barbs swept back from the tip
+                let back = base.sub(&tip).mul(0.3);
+                let lines = vec![
+                    (base, tip),
+                    (tip, tip.add(&back.rotate(0.5))),
+                    (tip, tip.add(&back.rotate(-0.5))),
+                ];
+                for (start, end) in lines {
+                    push_line(&mut vertices, &start, &end, &color);
+                }
+                y += spacing;
+            }
+            x += spacing;
+        }
+        self.draw_vertices(&vertices, &camera.view_projection(), gl::LINES);
+    }
+
+    // Debug overlay for collisions: the broad phase grid with occupied cells boxed, each body's
+    // bounding circle, collision perimeter and velocity, and everything that touched last tick.
+    // Drawn as of the last tick rather than interpolated, since that's what collision detection saw.
+    pub fn draw_debug(&mut self, world: &World, camera: &Camera) {
+        let (min, max) = camera.visible_bounds();
+        let visible = |position: &Vector, radius: f32| {
+            position.x + radius >= min.x && position.x - radius <= max.x && position.y + radius >= min.y && position.y - radius <= max.y
+        };
+        let mut vertices: Vec<f32> = Vec::new();
+
+        let grid = world.get_grid();
+        let cell = grid.cell_size();
+        let mut x = (min.x / cell).floor() * cell;
+        while x <= max.x {
+            push_line(&mut vertices, &Vector{x, y: min.y}, &Vector{x, y: max.y}, &GRID_COLOR);
+            x += cell;
+        }
+        let mut y = (min.y / cell).floor() * cell;
+        while y <= max.y {
+            push_line(&mut vertices, &Vector{x: min.x, y}, &Vector{x: max.x, y}, &GRID_COLOR);
+            y += cell;
+        }
+        for (cell_x, cell_y) in grid.occupied_cells() {
+            let half = cell / 2.0;
+            let center = Vector{x: (cell_x as f32 + 0.5) * cell, y: (cell_y as f32 + 0.5) * cell};
+            if !visible(&center, half) {
+                continue;
+            }
+            // Inset so the box doesn't hide the grid lines
+            let half = half - 2.0;
+            let corners = vec![
+                Vector{x: -half, y: -half},
+                Vector{x: half, y: -half},
+                Vector{x: half, y: half},
+                Vector{x: -half, y: half},
+            ];
+            push_polygon(&mut vertices, &center, &corners, &OCCUPIED_CELL_COLOR);
+        }
+
+        for ice in world.get_ices() {
+            let radius = ice.size as f32;
+            if !visible(&ice.position, radius) {
+                continue;
+            }
+            push_circle(&mut vertices, &ice.position, radius, &BOUNDS_COLOR);
+            push_polygon(&mut vertices, &ice.position, &ice.perimeter, &PERIMETER_COLOR);
+            push_line(&mut vertices, &ice.position, &ice.position.add(&ice.direction.mul(DEBUG_VELOCITY_SCALE)), &VELOCITY_COLOR);
+        }
+        let boat = world.get_boat();
+        push_circle(&mut vertices, &boat.position, boat.radius(), &BOUNDS_COLOR);
+        push_polygon(&mut vertices, &boat.position, &boat.perimeter, &PERIMETER_COLOR);
+        push_line(&mut vertices, &boat.position, &boat.position.add(&boat.direction.mul(DEBUG_VELOCITY_SCALE)), &VELOCITY_COLOR);
+
+        // Each colliding pair is joined center to center, with its contact points crossed and
+        // the normal drawn from their middle
+        let position_of = |id| {
+            if id == boat.id {
+                return Some(boat.position);
+            }
+            return world.get_ice(id).map(|ice| ice.position);
+        };
+        for collision in world.get_collisions() {
+            // Either side may have broken up or melted since
+            if let (Some(a), Some(b)) = (position_of(collision.a), position_of(collision.b)) {
+                push_line(&mut vertices, &a, &b, &CONTACT_COLOR);
+            }
+            let arm = Vector{x: CONTACT_MARKER_SIZE, y: CONTACT_MARKER_SIZE};
+            for point in &collision.contact.points {
+                push_line(&mut vertices, &point.sub(&arm), &point.add(&arm), &CONTACT_COLOR);
+                let arm = arm.rotate(std::f32::consts::PI / 2.0);
+                push_line(&mut vertices, &point.sub(&arm), &point.add(&arm), &CONTACT_COLOR);
+            }
+            let center = collision.contact.center();
+            push_line(&mut vertices, &center, &center.add(&collision.contact.normal.mul(DEBUG_NORMAL_LENGTH)), &CONTACT_COLOR);
+        }
+
+        self.draw_vertices(&vertices, &camera.view_projection(), gl::LINES);
+    }
+
+    // Re-uploads every berg's outline, if any have changed since last time
+    fn upload_outlines(&mut self, world: &World) {
+        let generation = world.get_shape_generation();
+        if self.uploaded_generation == Some(generation) {
+            return;
+        }
+        self.uploaded_generation = Some(generation);
+
+        let mut points: Vec<f32> = Vec::new();
+        let mut indices: Vec<i32> = Vec::new();
+        self.max_sides = 0;
+        for ice in world.get_ices() {
+            let outline = ice.get_outline();
+            indices.push((points.len() / 2) as i32);
+            indices.push(outline.len() as i32);
+            self.max_sides = self.max_sides.max(outline.len() as i32);
+            for p in outline {
+                points.push(p.x);
+                points.push(p.y);
+            }
+        }
+
+        unsafe {
+            upload(gl::TEXTURE_BUFFER, self.outline_buffer, &points, gl::STATIC_DRAW);
+            upload(gl::ARRAY_BUFFER, self.outline_index_vbo, &indices, gl::STATIC_DRAW);
+        }
+    }
+
+    fn draw_ice(&mut self, world: &World, view_projection: &[f32; 16], alpha: f32) {
+        let ices = world.get_ices();
+        if ices.is_empty() {
+            return;
+        }
+        self.upload_outlines(world);
+
+        // Same order as the outlines, so instance i lines up with outline i
+        let mut instances: Vec<f32> = Vec::with_capacity(ices.len() * INSTANCE_FLOATS);
+        for ice in ices {
+            let (position, rotation) = ice.interpolated(alpha);
+            instances.push(position.x);
+            instances.push(position.y);
+            instances.push(rotation);
+            instances.extend_from_slice(&ICE_COLOR);
+        }
+
+        self.ice_program.set_used();
+        unsafe {
+            upload(gl::ARRAY_BUFFER, self.instance_vbo, &instances, gl::STREAM_DRAW);
+
+            gl::UniformMatrix4fv(self.ice_program.get_uniform_location("ViewProjection"), 1, gl::FALSE, view_projection.as_ptr());
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_BUFFER, self.outline_texture);
+            gl::Uniform1i(self.ice_program.get_uniform_location("Outlines"), 0);
+
+            gl::BindVertexArray(self.ice_vao);
+            gl::DrawArraysInstanced(gl::TRIANGLES, 0, 3 * self.max_sides, ices.len() as i32);
+            gl::BindVertexArray(0);
+            gl::BindTexture(gl::TEXTURE_BUFFER, 0);
+        }
+    }
+
+    // Streams interleaved xyz rgb vertices through the shared buffer and draws them with the
+    // given primitive mode
+    fn draw_vertices(&mut self, vertices: &[f32], view_projection: &[f32; 16], mode: gl::types::GLenum) {
+        if vertices.is_empty() {
+            return;
+        }
+        let num_indices = vertices.len() as i32 / 6;
+
+        self.program.set_used();
+        unsafe {
+            gl::UniformMatrix4fv(self.program.get_uniform_location("ViewProjection"), 1, gl::FALSE, view_projection.as_ptr());
+            upload(gl::ARRAY_BUFFER, self.vbo, vertices, gl::STREAM_DRAW);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(
+                mode,          // mode
+                0,             // starting index in the enabled arrays
+                num_indices,   // number of indices to be rendered
+            );
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.outline_texture);
+            for buffer in &[self.vbo, self.instance_vbo, self.outline_index_vbo, self.outline_buffer] {
+                gl::DeleteBuffers(1, buffer);
+            }
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteVertexArrays(1, &self.ice_vao);
+        }
+    }
+}
+
+// Replaces a buffer's contents. Respecifying the whole store each time lets the driver hand
+// back fresh memory rather than waiting on draws still using the old contents.
+pub unsafe fn upload<T>(target: gl::types::GLenum, buffer: gl::types::GLuint, data: &[T], usage: gl::types::GLenum) {
+    gl::BindBuffer(target, buffer);
+    gl::BufferData(
+        target,
+        std::mem::size_of_val(data) as gl::types::GLsizeiptr, // size of data in bytes
+        data.as_ptr() as *const gl::types::GLvoid,
+        usage,
+    );
+    gl::BindBuffer(target, 0);
+}
+
+// Appends a world space point in the xyz rgb layout draw_vertices expects
+fn push_vertex(vertices: &mut Vec<f32>, point: &Vector, color: &[f32; 3]) {
+    vertices.push(point.x);
+    vertices.push(point.y);
+    vertices.push(0.0);
+    vertices.extend_from_slice(color);
+}
+
+// Appends a line segment for drawing with gl::LINES
+fn push_line(vertices: &mut Vec<f32>, start: &Vector, end: &Vector, color: &[f32; 3]) {
+    push_vertex(vertices, start, color);
+    push_vertex(vertices, end, color);
+}
+
+// Appends the outline of a polygon given relative to center, as line segments
+fn push_polygon(vertices: &mut Vec<f32>, center: &Vector, points: &[Vector], color: &[f32; 3]) {
+    for i in 0..points.len() {
+        let next = points[(i + 1) % points.len()];
+        push_line(vertices, &center.add(&points[i]), &center.add(&next), color);
+    }
+}
+
+fn push_circle(vertices: &mut Vec<f32>, center: &Vector, radius: f32, color: &[f32; 3]) {
+    let points: Vec<Vector> = (0..CIRCLE_SEGMENTS)
+        .map(|i| Vector{x: 0.0, y: -radius}.rotate(i as f32 * 2.0 * std::f32::consts::PI / CIRCLE_SEGMENTS as f32))
+        .collect();
+    push_polygon(vertices, center, &points, color);
+}
@@ -0,0 +1,182 @@
+use crate::keyboard_state::KeyboardState;
+use crate::SIM_DT;
+use std::fs;
+
+const REPLAY_HEADER: &str = "endurance-replay";
+// 2: worlds are generated with PCG, so version 1 seeds no longer give the same ice
+// 3: worlds get a current field, drawn from the seed before the ice
+// 4: and a wind, drawn before the ice too
+// 5: and a water temperature field, drawn before the ice too
+// 6: fuel caches are placed after the ice, changing everything drawn from the rng later
+// 7: berg outlines come from the shape generator, picking a kind for each berg
+const REPLAY_VERSION: u32 = 7;
+
+// A play session: the world seed plus the input for every simulation tick. Since the simulation
+// is deterministic for a given seed and fixed dt, this is enough to reproduce the session exactly
+// on the same build. Physics changes between builds will make old replays diverge.
+//
+// Saved as text, with held keys run-length encoded:
+//   endurance-replay 7
+//   seed 42
+//   dt 0.016666668
+//   120 1000
+//   35 1100
+// where each input line is "<ticks> <w><a><s><d>".
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub inputs: Vec<KeyboardState>,
+}
+
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        return Replay{seed, inputs: Vec::new()};
+    }
+
+    pub fn record(&mut self, keyboard_state: &KeyboardState) {
+        self.inputs.push(*keyboard_state);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.inputs.len();
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let mut out = format!("{} {}\nseed {}\ndt {}\n", REPLAY_HEADER, REPLAY_VERSION, self.seed, SIM_DT);
+        let mut i = 0;
+        while i < self.inputs.len() {
+            let keys = encode_keys(&self.inputs[i]);
+            let mut run = 1;
+            while i + run < self.inputs.len() && encode_keys(&self.inputs[i + run]) == keys {
+                run += 1;
+            }
+            out.push_str(&format!("{} {}\n", run, keys));
+            i += run;
+        }
+        return fs::write(path, out).map_err(|e| format!("Couldn't write replay {}: {}", path, e));
+    }
+
+    pub fn load(path: &str) -> Result<Replay, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("Couldn't read replay {}: {}", path, e))?;
+        let mut lines = contents.lines();
+
+        let header = lines.next().unwrap_or("");
+        if header != format!("{} {}", REPLAY_HEADER, REPLAY_VERSION) {
+            return Err(format!("{} is not a version {} replay (header: {:?})", path, REPLAY_VERSION, header));
+        }
+        let seed = parse_field(lines.next(), "seed")?;
+        let dt: f32 = parse_field(lines.next(), "dt")?;
+        if dt != SIM_DT {
+            return Err(format!("Replay was recorded with dt {}, but the simulation steps at {}", dt, SIM_DT));
+        }
+
+        let mut replay = Replay::new(seed);
+        for line in lines.filter(|l| !l.is_empty()) {
+            let mut parts = line.split_whitespace();
+            let run: usize = parts.next().and_then(|r| r.parse().ok())
+                .ok_or(format!("Bad replay line: {:?}", line))?;
+            let keyboard_state = parts.next().and_then(decode_keys)
+                .ok_or(format!("Bad replay line: {:?}", line))?;
+            for _ in 0..run {
+                replay.record(&keyboard_state);
+            }
+        }
+        return Ok(replay);
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(line: Option<&str>, name: &str) -> Result<T, String> {
+    let line = line.unwrap_or("");
+    let value = line.strip_prefix(name).map(|v| v.trim())
+        .ok_or(format!("Expected {} in replay, got {:?}", name, line))?;
+    return value.parse().map_err(|_| format!("Invalid {} in replay: {:?}", name, value));
+}
+
+// Escape isn't part of the simulation, so it isn't recorded
+fn encode_keys(keyboard_state: &KeyboardState) -> String {
+    return [keyboard_state.w, keyboard_state.a, keyboard_state.s, keyboard_state.d].iter()
+        .map(|held| if *held { '1' } else { '0' })
+        .collect();
+}
+
+fn decode_keys(keys: &str) -> Option<KeyboardState> {
+    let held: Vec<bool> = keys.chars().map(|c| c == '1').collect();
+    if held.len() != 4 || keys.chars().any(|c| c != '0' && c != '1') {
+        return None;
+    }
+    return Some(KeyboardState{w: held[0], a: held[1], s: held[2], d: held[3], ..KeyboardState::default()});
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        return std::env::temp_dir().join(format!("endurance-{}-{}", std::process::id(), name)).to_string_lossy().into_owned();
+    }
+
+    fn keys(w: bool, a: bool, s: bool, d: bool) -> KeyboardState {
+        return KeyboardState{w, a, s, d, ..KeyboardState::default()};
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut replay = Replay::new(42);
+        for _ in 0..120 {
+            replay.record(&keys(true, false, false, false));
+        }
+        for _ in 0..35 {
+            replay.record(&keys(true, true, false, false));
+        }
+        replay.record(&keys(false, false, true, true));
+
+        let path = temp_path("round-trip.replay");
+        replay.save(&path).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        // One line per run of identical input
+        let runs: Vec<&str> = contents.lines().skip(3).collect();
+        assert_eq!(runs, vec!["120 1000", "35 1100", "1 0011"]);
+
+        let loaded = loaded.unwrap();
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.len(), replay.len());
+        for (a, b) in loaded.inputs.iter().zip(&replay.inputs) {
+            assert_eq!(encode_keys(a), encode_keys(b));
+        }
+    }
+
+    #[test]
+    fn escape_is_not_recorded() {
+        let held = KeyboardState{esc: true, ..keys(false, true, false, false)};
+        assert_eq!(encode_keys(&held), "0100");
+        assert!(!decode_keys("0100").unwrap().esc);
+    }
+
+    #[test]
+    fn decode_rejects_bad_keys() {
+        assert!(decode_keys("101").is_none());
+        assert!(decode_keys("10101").is_none());
+        assert!(decode_keys("10x1").is_none());
+    }
+
+    #[test]
+    fn load_rejects_other_versions() {
+        let path = temp_path("old.replay");
+        fs::write(&path, format!("{} {}\nseed 1\ndt {}\n", REPLAY_HEADER, REPLAY_VERSION - 1, SIM_DT)).unwrap();
+        let result = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_rejects_bad_lines() {
+        let path = temp_path("bad-line.replay");
+        fs::write(&path, format!("{} {}\nseed 1\ndt {}\nten 1000\n", REPLAY_HEADER, REPLAY_VERSION, SIM_DT)).unwrap();
+        let result = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::vector::Vector;
+use crate::geometry::{convex_hull, polygon_centroid};
+use crate::BERG_MIN_SIZE;
+use rand::Rng;
+use std::f32::consts::PI;
+
+// The sorts of ice a field is made of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BergKind {
+    // Rough, roundish berg
+    Classic,
+    // Long and flat sided, calved off an ice shelf
+    Tabular,
+    // Small and jagged
+    Growler,
+    // Thin, smooth sheet ice
+    Floe,
+}
+
+// Knobs for generate_outline. Points are laid out around an ellipse and wobbled, then the convex
+// hull is taken, so whatever the settings the result is a valid convex piece.
+#[derive(Debug, Clone, Copy)]
+pub struct ShapeParams {
+    pub min_vertices: u32,
+    pub max_vertices: u32,
+    // Length over breadth
+    pub min_elongation: f32,
+    pub max_elongation: f32,
+    // Each point sits between this fraction of the full radius and the full radius
+    pub min_radius: f32,
+    // How far each point can stray from even spacing around the outline, as a fraction of the spacing
+    pub angle_jitter: f32,
+}
+
+impl BergKind {
+    pub fn params(&self) -> ShapeParams {
+        return match self {
+            BergKind::Classic => ShapeParams{min_vertices: 5, max_vertices: 8, min_elongation: 1.0, max_elongation: 1.4, min_radius: 0.5, angle_jitter: 0.3},
+            BergKind::Tabular => ShapeParams{min_vertices: 4, max_vertices: 6, min_elongation: 1.8, max_elongation: 3.0, min_radius: 0.85, angle_jitter: 0.15},
+            BergKind::Growler => ShapeParams{min_vertices: 6, max_vertices: 10, min_elongation: 1.0, max_elongation: 1.6, min_radius: 0.35, angle_jitter: 0.45},
+            BergKind::Floe => ShapeParams{min_vertices: 10, max_vertices: 16, min_elongation: 1.0, max_elongation: 1.4, min_radius: 0.9, angle_jitter: 0.2},
+        };
+    }
+
+    // Small pieces are mostly growlers, bigger ones a mix of everything else
+    pub fn random<R: Rng>(size: u32, rng: &mut R) -> BergKind {
+        let roll = rng.gen_range(0.0, 1.0);
+        if size < 2 * BERG_MIN_SIZE {
+            return if roll < 0.7 { BergKind::Growler } else { BergKind::Floe };
+        }
+        if roll < 0.5 {
+            return BergKind::Classic;
+        }
+        if roll < 0.7 {
+            return BergKind::Tabular;
+        }
+        return BergKind::Floe;
+    }
+}
+
+// Convex outline centered on its centroid, reaching at most size from it
+pub fn generate_outline<R: Rng>(params: &ShapeParams, size: u32, rng: &mut R) -> Vec<Vector> {
+    let num_vertices = rng.gen_range(params.min_vertices, params.max_vertices + 1).max(3);
+    let elongation = rng.gen_range(params.min_elongation, params.max_elongation + f32::EPSILON);
+    let orientation = rng.gen_range(0.0, 2.0 * PI);
+    let spacing = 2.0 * PI / num_vertices as f32;
+
+    let mut points = Vec::new();
+    for i in 0..num_vertices {
+        let angle = (i as f32 + rng.gen_range(-params.angle_jitter, params.angle_jitter + f32::EPSILON)) * spacing;
+        let radius = rng.gen_range(params.min_radius, 1.0 + f32::EPSILON);
+        // Ellipse with its long axis up, squashed across by the elongation
+        let point = Vector{x: angle.sin() / elongation, y: -angle.cos()}.mul(radius);
+        points.push(point.rotate(orientation));
+    }
+
+    let hull = convex_hull(&points);
+    let center = polygon_centroid(&hull);
+    let hull: Vec<Vector> = hull.iter().map(|p| p.sub(&center)).collect();
+
+    // Recentering can push a point past the bound, so scale everything back inside it
+    let reach = hull.iter().map(|p| p.length()).fold(0.0, f32::max);
+    return hull.iter().map(|p| p.mul(size as f32 / reach)).collect();
+}
@@ -0,0 +1,244 @@
+use crate::world::World;
+use crate::wind::Wind;
+use crate::temperature::Temperature;
+use crate::{FREEZING_POINT, BOAT_FUEL_CAPACITY};
+use crate::boat::Integrity;
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::fs;
+
+// Bump whenever the serialized shape of World (or anything in it) changes, and add a step to
+// migrate_json that upgrades the previous version
+pub const SNAPSHOT_VERSION: u32 = 9;
+
+// Binary snapshots start with this, followed by the version as a little endian u32
+const BINARY_MAGIC: &[u8; 4] = b"ENDS";
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    world: &'a World,
+}
+
+// The version is checked (and migrated) on the raw JSON before this is deserialized
+#[derive(Deserialize)]
+struct Snapshot {
+    world: World,
+}
+
+// Saves the full world state. Paths ending in .json are written as (readable, large) JSON,
+// anything else as compact bincode.
+pub fn save(world: &World, path: &str) -> Result<(), String> {
+    let snapshot = SnapshotRef{version: SNAPSHOT_VERSION, world};
+    let bytes = if path.ends_with(".json") {
+        serde_json::to_vec_pretty(&snapshot).map_err(|e| format!("Couldn't serialize snapshot: {}", e))?
+    } else {
+        let mut bytes = BINARY_MAGIC.to_vec();
+        bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        let mut body = bincode::serialize(world).map_err(|e| format!("Couldn't serialize snapshot: {}", e))?;
+        bytes.append(&mut body);
+        bytes
+    };
+    return fs::write(path, bytes).map_err(|e| format!("Couldn't write snapshot {}: {}", path, e));
+}
+
+// Loads a snapshot written by save, in either format
+pub fn load(path: &str) -> Result<World, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Couldn't read snapshot {}: {}", path, e))?;
+    let mut world = if bytes.starts_with(BINARY_MAGIC) {
+        load_binary(&bytes[BINARY_MAGIC.len()..])?
+    } else {
+        load_json(&bytes)?
+    };
+    world.rebuild_indices();
+    return Ok(world);
+}
+
+// Bincode isn't self describing, so there's no way to read an older layout - only the current
+// version is accepted
+fn load_binary(bytes: &[u8]) -> Result<World, String> {
+    if bytes.len() < 4 {
+        return Err("Binary snapshot is truncated".to_string());
+    }
+    let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    if version != SNAPSHOT_VERSION {
+        return Err(format!(
+            "Binary snapshot is version {}, but only version {} can be loaded. Binary snapshots can't be migrated, re-save the world as .json with a matching build.",
+            version, SNAPSHOT_VERSION));
+    }
+    return bincode::deserialize(&bytes[4..]).map_err(|e| format!("Corrupt binary snapshot: {}", e));
+}
+
+fn load_json(bytes: &[u8]) -> Result<World, String> {
+    let value: Value = serde_json::from_slice(bytes).map_err(|e| format!("Snapshot is neither binary nor valid JSON: {}", e))?;
+    let version = value.get("version").and_then(|v| v.as_u64())
+        .ok_or("Snapshot has no version field".to_string())? as u32;
+    if version > SNAPSHOT_VERSION {
+        return Err(format!("Snapshot is version {}, newer than this build supports ({})", version, SNAPSHOT_VERSION));
+    }
+
+    let value = migrate_json(value, version)?;
+    let snapshot: Snapshot = serde_json::from_value(value).map_err(|e| format!("Corrupt snapshot: {}", e))?;
+    return Ok(snapshot.world);
+}
+
+// Upgrades an older JSON snapshot one version at a time until it's current
+fn migrate_json(mut value: Value, version: u32) -> Result<Value, String> {
+    if version == SNAPSHOT_VERSION {
+        return Ok(value);
+    }
+    match version {
+        // 2 added the fracture count to stats
+        1 => value["world"]["stats"]["fractures"] = Value::from(0),
+        // 3 added ocean currents, older worlds had still water
+        2 => value["world"]["current"] = Value::from("Still"),
+        // 4 added wind, older worlds were becalmed
+        3 => value["world"]["wind"] = serde_json::to_value(Wind::calm()).map_err(|e| e.to_string())?,
+        // 5 added melting and freezing. Older worlds get water right at freezing, so they neither.
+        4 => {
+            value["world"]["temperature"] = serde_json::to_value(Temperature::uniform(FREEZING_POINT)).map_err(|e| e.to_string())?;
+            value["world"]["stats"]["melted"] = Value::from(0);
+            value["world"]["stats"]["frozen"] = Value::from(0);
+        },
+        // 6 added hull damage
+        5 => value["world"]["boat"]["integrity"] = serde_json::to_value(Integrity::sound()).map_err(|e| e.to_string())?,
+        // 7 added fuel. Older boats start with full bunkers and no caches to top up from.
+        6 => {
+            value["world"]["boat"]["fuel"] = Value::from(BOAT_FUEL_CAPACITY);
+            value["world"]["fuel_caches"] = Value::Array(Vec::new());
+        },
+        // 8 added pack ice pressure
+        7 => {
+            value["world"]["boat"]["pressure"] = Value::from(0.0);
+            value["world"]["boat"]["beset_time"] = Value::from(0.0);
+        },
+        // 9 dropped the per-berg render triangles, the renderer fans them from the outline itself
+        8 => {
+            if let Some(ices) = value["world"]["ices"].as_array_mut() {
+                for ice in ices {
+                    if let Some(ice) = ice.as_object_mut() {
+                        ice.remove("triangles");
+                    }
+                }
+            }
+        },
+        _ => return Err(format!("Don't know how to migrate a version {} snapshot", version)),
+    }
+    value["version"] = Value::from(version + 1);
+    return migrate_json(value, version + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::current::CurrentField;
+    use crate::keyboard_state::KeyboardState;
+    use crate::SIM_DT;
+
+    fn temp_path(name: &str) -> String {
+        return std::env::temp_dir().join(format!("endurance-{}-{}", std::process::id(), name)).to_string_lossy().into_owned();
+    }
+
+    fn small_world() -> World {
+        let mut world = World::new(800, 600, 7);
+        world.init_with_random_ice(10);
+        for _ in 0..30 {
+            world.tick(&KeyboardState{w: true, ..KeyboardState::default()}, SIM_DT);
+        }
+        return world;
+    }
+
+    fn round_trip(name: &str) {
+        let mut world = small_world();
+        let path = temp_path(name);
+        save(&world, &path).unwrap();
+        let loaded = load(&path);
+        fs::remove_file(&path).unwrap();
+
+        let mut loaded = loaded.unwrap();
+        assert_eq!(loaded.checksum(), world.checksum());
+        assert_eq!(loaded.get_ices().len(), world.get_ices().len());
+        // Derived lookups are rebuilt, so the loaded world carries on exactly as the original
+        for _ in 0..30 {
+            world.tick(&KeyboardState::default(), SIM_DT);
+            loaded.tick(&KeyboardState::default(), SIM_DT);
+        }
+        assert_eq!(loaded.checksum(), world.checksum());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        round_trip("round-trip.json");
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        round_trip("round-trip.snapshot");
+    }
+
+    #[test]
+    fn binary_rejects_other_versions() {
+        let mut bytes = (SNAPSHOT_VERSION - 1).to_le_bytes().to_vec();
+        bytes.extend(bincode::serialize(&small_world()).unwrap());
+        assert!(load_binary(&bytes).is_err());
+        assert!(load_binary(&bytes[..2]).is_err());
+    }
+
+    #[test]
+    fn json_rejects_newer_versions() {
+        let value = serde_json::json!({"version": SNAPSHOT_VERSION + 1, "world": {}});
+        assert!(load_json(&serde_json::to_vec(&value).unwrap()).is_err());
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        // Strip everything added since version 1 from a current snapshot
+        let world = small_world();
+        let mut value = serde_json::to_value(SnapshotRef{version: 1, world: &world}).unwrap();
+        {
+            let world = value["world"].as_object_mut().unwrap();
+            for field in &["current", "wind", "temperature", "fuel_caches"] {
+                world.remove(*field);
+            }
+        }
+        {
+            let stats = value["world"]["stats"].as_object_mut().unwrap();
+            for field in &["fractures", "melted", "frozen"] {
+                stats.remove(*field);
+            }
+        }
+        {
+            let boat = value["world"]["boat"].as_object_mut().unwrap();
+            for field in &["integrity", "fuel", "pressure", "beset_time"] {
+                boat.remove(*field);
+            }
+        }
+        for ice in value["world"]["ices"].as_array_mut().unwrap() {
+            ice["triangles"] = Value::Array(Vec::new());
+        }
+        // Version 1 can't be read as is
+        assert!(serde_json::from_value::<Snapshot>(value.clone()).is_err());
+
+        let value = migrate_json(value, 1).unwrap();
+        assert_eq!(value["version"], Value::from(SNAPSHOT_VERSION));
+        assert!(value["world"]["ices"][0].get("triangles").is_none());
+
+        let migrated = serde_json::from_value::<Snapshot>(value).unwrap().world;
+        assert!(matches!(migrated.get_current(), CurrentField::Still));
+        assert_eq!(migrated.get_wind().velocity().length(), 0.0);
+        assert!(migrated.get_fuel_caches().is_empty());
+        let boat = migrated.get_boat();
+        assert_eq!(boat.fuel, BOAT_FUEL_CAPACITY);
+        assert_eq!(boat.pressure, 0.0);
+        assert_eq!(boat.integrity.bow.min(boat.integrity.midships).min(boat.integrity.stern), 1.0);
+        let stats = migrated.get_stats();
+        assert_eq!((stats.fractures, stats.melted, stats.frozen), (0, 0, 0));
+        assert_eq!(stats.ticks, world.get_stats().ticks);
+        assert_eq!(migrated.get_ices().len(), world.get_ices().len());
+    }
+
+    #[test]
+    fn migrate_rejects_unknown_versions() {
+        assert!(migrate_json(serde_json::json!({}), 0).is_err());
+    }
+}
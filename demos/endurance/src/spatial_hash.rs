@@ -0,0 +1,216 @@
+use crate::vector::Vector;
+use std::collections::BTreeMap;
+
+// Inclusive range of grid cells covered by an object's bounding box
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CellRange {
+    min_x: i32,
+    min_y: i32,
+    max_x: i32,
+    max_y: i32,
+}
+
+// Uniform grid broad phase. Objects are referred to by index and inserted into every cell their
+// bounding circle touches, so objects of any size are handled. Cells are kept in a BTreeMap so
+// iteration order (and therefore the simulation) is deterministic.
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: BTreeMap<(i32, i32), Vec<usize>>,
+    // Cells each object currently occupies, indexed by object index
+    ranges: Vec<Option<CellRange>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: u32) -> SpatialHash {
+        return SpatialHash{cell_size: cell_size as f32, cells: BTreeMap::new(), ranges: Vec::new()};
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        return self.cell_size;
+    }
+
+    // Cells with at least one object in them
+    pub fn occupied_cells(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.cells.keys().copied()
+    }
+
+    // Grid cell containing a point
+    pub fn cell_of(&self, position: &Vector) -> (i32, i32) {
+        return ((position.x / self.cell_size).floor() as i32, (position.y / self.cell_size).floor() as i32);
+    }
+
+    fn range_of(&self, position: &Vector, radius: f32) -> CellRange {
+        let (min_x, min_y) = self.cell_of(&Vector{x: position.x - radius, y: position.y - radius});
+        let (max_x, max_y) = self.cell_of(&Vector{x: position.x + radius, y: position.y + radius});
+        return CellRange{min_x, min_y, max_x, max_y};
+    }
+
+    // Inserts an object, or moves it if it's already present. Only touches the grid when the
+    // set of covered cells actually changes, which for most objects on most ticks it doesn't.
+    pub fn update(&mut self, index: usize, position: &Vector, radius: f32) {
+        let range = self.range_of(position, radius);
+        if index >= self.ranges.len() {
+            self.ranges.resize(index + 1, None);
+        }
+        if self.ranges[index] == Some(range) {
+            return;
+        }
+        self.remove(index);
+        for x in range.min_x..=range.max_x {
+            for y in range.min_y..=range.max_y {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+        self.ranges[index] = Some(range);
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        let range = match self.ranges.get_mut(index).and_then(|r| r.take()) {
+            Some(range) => range,
+            None => return,
+        };
+        for x in range.min_x..=range.max_x {
+            for y in range.min_y..=range.max_y {
+                if let Some(cell) = self.cells.get_mut(&(x, y)) {
+                    cell.retain(|i| *i != index);
+                    if cell.is_empty() {
+                        self.cells.remove(&(x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    // Mirrors Vec::swap_remove, so the grid stays in step with a Vec of objects: removes index,
+    // and the object that was last is renumbered to index
+    pub fn swap_remove(&mut self, index: usize) {
+        self.remove(index);
+        let last = self.ranges.len() - 1;
+        if index != last {
+            if let Some(range) = self.ranges[last] {
+                for x in range.min_x..=range.max_x {
+                    for y in range.min_y..=range.max_y {
+                        if let Some(cell) = self.cells.get_mut(&(x, y)) {
+                            for i in cell.iter_mut().filter(|i| **i == last) {
+                                *i = index;
+                            }
+                        }
+                    }
+                }
+            }
+            self.ranges[index] = self.ranges[last];
+        }
+        self.ranges.pop();
+    }
+
+    // Objects sharing at least one cell with the given circle, each reported once
+    pub fn query(&self, position: &Vector, radius: f32) -> Vec<usize> {
+        let range = self.range_of(position, radius);
+        let mut found = Vec::new();
+        for x in range.min_x..=range.max_x {
+            for y in range.min_y..=range.max_y {
+                if let Some(cell) = self.cells.get(&(x, y)) {
+                    found.extend(cell);
+                }
+            }
+        }
+        found.sort();
+        found.dedup();
+        return found;
+    }
+
+    // Candidate colliding pairs (lower index first), each reported exactly once
+    pub fn query_pairs(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.iter().flat_map(move |(cell, items)| {
+            (0..items.len()).flat_map(move |i| (i + 1..items.len()).map(move |j| (items[i], items[j])))
+                .filter(move |(a, b)| self.first_shared_cell(*a, *b) == *cell)
+                .map(|(a, b)| if a < b { (a, b) } else { (b, a) })
+        })
+    }
+
+    // Objects spanning several cells can share more than one, so a pair is only reported from
+    // the top left cell of the overlap of their ranges
+    fn first_shared_cell(&self, a: usize, b: usize) -> (i32, i32) {
+        let range_a = self.ranges[a].unwrap();
+        let range_b = self.ranges[b].unwrap();
+        return (range_a.min_x.max(range_b.min_x), range_a.min_y.max(range_b.min_y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(grid: &SpatialHash) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = grid.query_pairs().collect();
+        pairs.sort();
+        return pairs;
+    }
+
+    #[test]
+    fn pair_sharing_several_cells_is_reported_once() {
+        let mut grid = SpatialHash::new(10);
+        // Both cover a 4x4 block of cells, overlapping in a 3x3 block
+        grid.update(0, &Vector{x: 20.0, y: 20.0}, 15.0);
+        grid.update(1, &Vector{x: 25.0, y: 25.0}, 15.0);
+        assert_eq!(pairs(&grid), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn pairs_are_lower_index_first() {
+        let mut grid = SpatialHash::new(10);
+        grid.update(1, &Vector{x: 5.0, y: 5.0}, 2.0);
+        grid.update(0, &Vector{x: 6.0, y: 6.0}, 2.0);
+        grid.update(2, &Vector{x: 500.0, y: 500.0}, 2.0);
+        assert_eq!(pairs(&grid), vec![(0, 1)]);
+    }
+
+    #[test]
+    fn query_reports_each_object_once() {
+        let mut grid = SpatialHash::new(10);
+        grid.update(0, &Vector{x: 0.0, y: 0.0}, 25.0);
+        assert_eq!(grid.query(&Vector{x: 0.0, y: 0.0}, 25.0), vec![0]);
+    }
+
+    #[test]
+    fn update_moves_object_out_of_old_cells() {
+        let mut grid = SpatialHash::new(10);
+        grid.update(0, &Vector{x: 5.0, y: 5.0}, 1.0);
+        grid.update(0, &Vector{x: 105.0, y: 5.0}, 1.0);
+        assert!(grid.query(&Vector{x: 5.0, y: 5.0}, 1.0).is_empty());
+        assert_eq!(grid.query(&Vector{x: 105.0, y: 5.0}, 1.0), vec![0]);
+        assert_eq!(grid.occupied_cells().count(), 1);
+    }
+
+    #[test]
+    fn swap_remove_renumbers_last_object() {
+        let mut grid = SpatialHash::new(10);
+        grid.update(0, &Vector{x: 5.0, y: 5.0}, 1.0);
+        grid.update(1, &Vector{x: 55.0, y: 5.0}, 1.0);
+        // Spans several cells, so every one of them has to be fixed up
+        grid.update(2, &Vector{x: 100.0, y: 100.0}, 15.0);
+
+        grid.swap_remove(0);
+        assert!(grid.query(&Vector{x: 5.0, y: 5.0}, 1.0).is_empty());
+        assert_eq!(grid.query(&Vector{x: 55.0, y: 5.0}, 1.0), vec![1]);
+        for corner in &[Vector{x: 86.0, y: 86.0}, Vector{x: 114.0, y: 114.0}] {
+            assert_eq!(grid.query(corner, 0.0), vec![0]);
+        }
+
+        // The renumbered object can still be moved and removed by its new index
+        grid.update(0, &Vector{x: 55.0, y: 6.0}, 1.0);
+        assert_eq!(pairs(&grid), vec![(0, 1)]);
+        grid.remove(0);
+        assert_eq!(grid.occupied_cells().count(), 1);
+    }
+
+    #[test]
+    fn swap_remove_of_last_object() {
+        let mut grid = SpatialHash::new(10);
+        grid.update(0, &Vector{x: 5.0, y: 5.0}, 1.0);
+        grid.update(1, &Vector{x: 55.0, y: 5.0}, 1.0);
+        grid.swap_remove(1);
+        assert!(grid.query(&Vector{x: 55.0, y: 5.0}, 1.0).is_empty());
+        assert_eq!(grid.query(&Vector{x: 5.0, y: 5.0}, 1.0), vec![0]);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::vector::Vector;
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+// A patch of warmer water, warmest at the center and fading out with distance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmPatch {
+    pub center: Vector,
+    pub radius: f32,
+    // Degrees above the background at the center
+    pub warmth: f32,
+}
+
+// Sea surface temperature over the world, degrees C
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Temperature {
+    pub base: f32,
+    pub patches: Vec<WarmPatch>,
+}
+
+impl Temperature {
+    // The same everywhere
+    pub fn uniform(base: f32) -> Temperature {
+        return Temperature{base, patches: Vec::new()};
+    }
+
+    // Cold water with a few warm patches scattered over the given area
+    pub fn random<R: Rng>(min: &Vector, max: &Vector, base: f32, num_patches: u32, max_warmth: f32, rng: &mut R) -> Temperature {
+        let mut patches = Vec::new();
+        for _ in 0..num_patches {
+            let center = Vector{x: rng.gen_range(min.x, max.x), y: rng.gen_range(min.y, max.y)};
+            let radius = rng.gen_range(200.0, 700.0);
+            let warmth = rng.gen_range(0.3, 1.0) * max_warmth;
+            patches.push(WarmPatch{center, radius, warmth});
+        }
+        return Temperature{base, patches};
+    }
+
+    pub fn at(&self, position: &Vector) -> f32 {
+        return self.patches.iter().fold(self.base, |temperature, patch| {
+            let r = position.sub(&patch.center);
+            temperature + patch.warmth * (-r.dot(&r) / (patch.radius * patch.radius)).exp()
+        });
+    }
+}
@@ -0,0 +1,16 @@
+#version 330 core
+
+in VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} IN;
+
+// Glyphs are white, so only their coverage (alpha) matters
+uniform sampler2D Atlas;
+
+out vec4 Color;
+
+void main()
+{
+    Color = vec4(IN.Color.rgb, IN.Color.a * texture(Atlas, IN.TexCoord).a);
+}
@@ -0,0 +1,218 @@
+use crate::render_gl::{Program, Shader};
+use crate::renderer::upload;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::rect::Rect;
+use sdl2::render::BlendMode;
+use sdl2::rwops::RWops;
+use sdl2::surface::Surface;
+use std::ffi::CString;
+
+// Compiled into the binary so the game doesn't depend on fonts installed on the machine
+const FONT: &[u8] = include_bytes!("../assets/DejaVuSansCondensed-Bold.ttf");
+// Printable ASCII is all the HUD needs. Anything else is drawn as FALLBACK_CHAR.
+const FIRST_CHAR: u8 = b' ';
+const LAST_CHAR: u8 = b'~';
+const FALLBACK_CHAR: char = '?';
+const ATLAS_WIDTH: u32 = 512;
+// Floats per vertex: screen position (2), atlas coordinates (2), color (4)
+const VERTEX_FLOATS: usize = 8;
+
+// Where a character's image sits in the atlas, in texels, and how far the pen moves after it
+#[derive(Debug, Clone, Copy)]
+struct Glyph {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    advance: f32,
+}
+
+// Draws text in screen pixels (top left origin) on top of whatever's already there.
+//
+// The font is rasterized once, when this is made, into a single texture holding every glyph.
+// Text is queued as quads over that texture and drawn in one call per flush.
+pub struct TextRenderer {
+    program: Program,
+    vao: gl::types::GLuint,
+    vbo: gl::types::GLuint,
+    texture: gl::types::GLuint,
+    atlas_height: u32,
+    glyphs: Vec<Glyph>,
+    line_height: f32,
+    vertices: Vec<f32>,
+}
+
+impl TextRenderer {
+    // Needs a current GL context. size is in points.
+    pub fn new(size: u16) -> Result<TextRenderer, String> {
+        let program = Program::from_shaders(&[
+            Shader::from_vert_source(&CString::new(include_str!("text.vert")).unwrap())?,
+            Shader::from_frag_source(&CString::new(include_str!("text.frag")).unwrap())?,
+        ])?;
+
+        // The font is only needed to build the atlas
+        let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+        let font = ttf_context.load_font_from_rwops(RWops::from_bytes(FONT)?, size)?;
+        let line_height = font.height() as f32;
+
+        let mut images = Vec::new();
+        for c in FIRST_CHAR..=LAST_CHAR {
+            let c = c as char;
+            let advance = font.find_glyph_metrics(c).map_or(0, |metrics| metrics.advance) as f32;
+            // Rendered white, so the shader can tint it any color
+            let mut image = font.render_char(c).blended(Color::RGBA(255, 255, 255, 255)).map_err(|e| e.to_string())?;
+            // Copy alpha across as is when packing, rather than blending onto the empty atlas
+            image.set_blend_mode(BlendMode::None)?;
+            images.push((image, advance));
+        }
+
+        // Shelf packing: left to right along rows as tall as the font
+        let mut glyphs = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        for (image, advance) in &images {
+            if x + image.width() > ATLAS_WIDTH {
+                x = 0;
+                y += font.height() as u32;
+            }
+            glyphs.push(Glyph{x, y, width: image.width(), height: image.height(), advance: *advance});
+            // A texel of space between glyphs stops neighbours bleeding in when filtering
+            x += image.width() + 1;
+        }
+        let atlas_height = y + font.height() as u32;
+
+        // RGBA32 is red, green, blue, alpha byte order whatever the platform, as GL expects
+        let mut atlas = Surface::new(ATLAS_WIDTH, atlas_height, PixelFormatEnum::RGBA32)?;
+        for ((image, _), glyph) in images.iter().zip(&glyphs) {
+            image.blit(None, &mut atlas, Rect::new(glyph.x as i32, glyph.y as i32, glyph.width, glyph.height))?;
+        }
+
+        let mut renderer = TextRenderer{
+            program,
+            vao: 0,
+            vbo: 0,
+            texture: 0,
+            atlas_height,
+            glyphs,
+            line_height,
+            vertices: Vec::new(),
+        };
+
+        let float_size = std::mem::size_of::<f32>();
+        let stride = (VERTEX_FLOATS * float_size) as gl::types::GLint;
+        unsafe {
+            gl::GenTextures(1, &mut renderer.texture);
+            gl::BindTexture(gl::TEXTURE_2D, renderer.texture);
+            // The surface can pad its rows out past the atlas width
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, (atlas.pitch() / 4) as gl::types::GLint);
+            atlas.with_lock(|pixels| {
+                gl::TexImage2D(
+                    gl::TEXTURE_2D,
+                    0,
+                    gl::RGBA8 as gl::types::GLint,
+                    ATLAS_WIDTH as gl::types::GLsizei,
+                    atlas_height as gl::types::GLsizei,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    pixels.as_ptr() as *const gl::types::GLvoid,
+                );
+            });
+            gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as gl::types::GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            gl::GenVertexArrays(1, &mut renderer.vao);
+            gl::GenBuffers(1, &mut renderer.vbo);
+            gl::BindVertexArray(renderer.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, renderer.vbo);
+            gl::EnableVertexAttribArray(0); // Position, in text.vert
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
+            gl::EnableVertexAttribArray(1); // TexCoord
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, (2 * float_size) as *const gl::types::GLvoid);
+            gl::EnableVertexAttribArray(2); // Color
+            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, (4 * float_size) as *const gl::types::GLvoid);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+
+        return Ok(renderer);
+    }
+
+    pub fn line_height(&self) -> f32 {
+        return self.line_height;
+    }
+
+    // Width in pixels text would take up on screen
+    pub fn measure(&self, text: &str) -> f32 {
+        return text.chars().map(|c| self.glyph(c).advance).sum();
+    }
+
+    // Queues a line of text with its top left corner at x, y. Nothing is drawn until flush.
+    pub fn queue(&mut self, text: &str, x: f32, y: f32, color: [f32; 4]) {
+        let mut pen = x;
+        for c in text.chars() {
+            let glyph = self.glyph(c);
+            let (left, top) = (pen.round(), y.round());
+            let (right, bottom) = (left + glyph.width as f32, top + glyph.height as f32);
+            let u0 = glyph.x as f32 / ATLAS_WIDTH as f32;
+            let v0 = glyph.y as f32 / self.atlas_height as f32;
+            let u1 = (glyph.x + glyph.width) as f32 / ATLAS_WIDTH as f32;
+            let v1 = (glyph.y + glyph.height) as f32 / self.atlas_height as f32;
+
+            // Two triangles per glyph
+            let corners = [
+                (left, top, u0, v0), (right, top, u1, v0), (right, bottom, u1, v1),
+                (left, top, u0, v0), (right, bottom, u1, v1), (left, bottom, u0, v1),
+            ];
+            for (px, py, u, v) in &corners {
+                self.vertices.extend_from_slice(&[*px, *py, *u, *v]);
+                self.vertices.extend_from_slice(&color);
+            }
+            pen += glyph.advance;
+        }
+    }
+
+    // Draws everything queued since the last flush over a screen of the given size in pixels
+    pub fn flush(&mut self, screen_width: u32, screen_height: u32) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let num_vertices = (self.vertices.len() / VERTEX_FLOATS) as i32;
+
+        self.program.set_used();
+        unsafe {
+            upload(gl::ARRAY_BUFFER, self.vbo, &self.vertices, gl::STREAM_DRAW);
+            gl::Uniform2f(self.program.get_uniform_location("ScreenSize"), screen_width as f32, screen_height as f32);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            gl::Uniform1i(self.program.get_uniform_location("Atlas"), 0);
+
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, num_vertices);
+            gl::BindVertexArray(0);
+            gl::Disable(gl::BLEND);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        self.vertices.clear();
+    }
+
+    fn glyph(&self, c: char) -> Glyph {
+        let c = if c as u32 >= FIRST_CHAR as u32 && c as u32 <= LAST_CHAR as u32 { c } else { FALLBACK_CHAR };
+        return self.glyphs[(c as u8 - FIRST_CHAR) as usize];
+    }
+}
+
+impl Drop for TextRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#version 330 core
+
+layout (location = 0) in vec2 Position;
+layout (location = 1) in vec2 TexCoord;
+layout (location = 2) in vec4 Color;
+
+// Window size in screen pixels, which Position is measured in from the top left
+uniform vec2 ScreenSize;
+
+out VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} OUT;
+
+void main()
+{
+    gl_Position = vec4(Position.x * 2.0 / ScreenSize.x - 1.0, 1.0 - Position.y * 2.0 / ScreenSize.y, 0.0, 1.0);
+    OUT.TexCoord = TexCoord;
+    OUT.Color = Color;
+}
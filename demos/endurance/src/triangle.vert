@@ -3,12 +3,15 @@
 layout (location = 0) in vec3 Position;
 layout (location = 1) in vec3 Color;
 
+// World space to normalized device coordinates, from Camera::view_projection
+uniform mat4 ViewProjection;
+
 out VS_OUTPUT {
     vec3 Color;
 } OUT;
 
 void main()
 {
-    gl_Position = vec4(Position, 1.0);
+    gl_Position = ViewProjection * vec4(Position, 1.0);
     OUT.Color = Color;
 }
\ No newline at end of file
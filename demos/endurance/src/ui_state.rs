@@ -0,0 +1,11 @@
+// Keys that change what's shown rather than what happens. Not part of the simulation input, so
+// never recorded.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct UiState {
+    // Toggles the current overlay
+    pub c: bool,
+    // Toggles the camera between following the boat and free panning
+    pub f: bool,
+    // Toggles the debug overlay
+    pub g: bool
+}
@@ -1,4 +1,6 @@
-#[derive(Debug, Clone, Copy, PartialEq)]
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Vector {
     pub x: f32,
     pub y: f32
@@ -21,5 +23,12 @@ impl Vector {
     pub fn mul(&self, scalar: f32) -> Vector {
         return Vector{x: self.x * scalar, y: self.y * scalar};
     }
+    pub fn length(&self) -> f32 {
+        return (self.x.powf(2.0) + self.y.powf(2.0)).sqrt();
+    }
+    // Rotates clockwise on screen (y points down) by angle radians
+    pub fn rotate(&self, angle: f32) -> Vector {
+        let (sin, cos) = angle.sin_cos();
+        return Vector{x: self.x * cos - self.y * sin, y: self.x * sin + self.y * cos};
+    }
 }
-
@@ -0,0 +1,62 @@
+use crate::vector::Vector;
+use crate::{WIND_MAX_SPEED, WIND_GUST_FACTOR, WIND_GUST_MIN_TICKS, WIND_GUST_MAX_TICKS, WIND_GUST_EASE, WIND_DRAG};
+use rand::Rng;
+use serde::{Serialize, Deserialize};
+
+// Wind over the whole world: a steady prevailing wind plus gusts that come and go. Velocities are
+// in the same units as body velocities.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Wind {
+    // Radians clockwise from straight up the screen, the way the wind blows towards
+    pub direction: f32,
+    pub strength: f32,
+    // Added on top of the prevailing wind, easing towards gust_target
+    pub gust: Vector,
+    gust_target: Vector,
+    // Tuning ticks until the next gust target is picked
+    gust_timer: f32,
+}
+
+impl Wind {
+    pub fn calm() -> Wind {
+        let zero = Vector{x: 0.0, y: 0.0};
+        return Wind{direction: 0.0, strength: 0.0, gust: zero, gust_target: zero, gust_timer: 0.0};
+    }
+
+    pub fn random<R: Rng>(rng: &mut R) -> Wind {
+        let mut wind = Wind::calm();
+        wind.direction = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+        wind.strength = rng.gen_range(0.0, WIND_MAX_SPEED);
+        return wind;
+    }
+
+    // Moves the gust along. Gusts are drawn from the world's rng so they replay identically.
+    pub fn update<R: Rng>(&mut self, steps: f32, rng: &mut R) {
+        self.gust_timer -= steps;
+        if self.gust_timer <= 0.0 {
+            // Gusts mostly strengthen or slacken the prevailing wind, and veer it a little
+            let speed = rng.gen_range(-1.0, 1.0) * WIND_GUST_FACTOR * self.strength;
+            let veer = rng.gen_range(-0.5, 0.5);
+            self.gust_target = Vector{x: 0.0, y: -1.0}.rotate(self.direction + veer).mul(speed);
+            self.gust_timer = rng.gen_range(WIND_GUST_MIN_TICKS, WIND_GUST_MAX_TICKS);
+        }
+        let remaining = (1.0 - WIND_GUST_EASE).powf(steps);
+        self.gust = self.gust_target.add(&self.gust.sub(&self.gust_target).mul(remaining));
+    }
+
+    pub fn prevailing(&self) -> Vector {
+        return Vector{x: 0.0, y: -1.0}.rotate(self.direction).mul(self.strength);
+    }
+
+    // What's blowing right now, gusts included
+    pub fn velocity(&self) -> Vector {
+        return self.prevailing().add(&self.gust);
+    }
+
+    // Change in velocity over the update for a body with the given mass and area exposed to the
+    // wind. Pushes towards the wind speed, so a body can't be blown faster than the wind.
+    pub fn push(&self, velocity: &Vector, area: f32, mass: f32, steps: f32) -> Vector {
+        let relative = self.velocity().sub(velocity);
+        return relative.mul((WIND_DRAG * area / mass * steps).min(1.0));
+    }
+}
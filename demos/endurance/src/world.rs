@@ -1,149 +1,398 @@
 use crate::ice::{Ice};
 use crate::boat::{Boat};
 use crate::vector::{Vector};
-use sdl2::render::{WindowCanvas};
 use rand::Rng;
-use crate::{BOAT_SIZE, ICE_DECEL_FACTOR, BERG_MIN_SIZE, BERG_MAX_SIZE, GRID_SIZE, BOAT_ACCELERATION};
+use rand::SeedableRng;
+use rand_pcg::Pcg64;
+use serde::{Serialize, Deserialize};
+use crate::{BOAT_SIZE, ICE_RESTITUTION, BERG_MIN_SIZE, BERG_MAX_SIZE, GRID_SIZE, BOAT_REVERSE_THROTTLE, TUNING_RATE, ICE_HARDNESS, FRACTURE_ANGLE_JITTER, NUM_GYRES, CURRENT_MAX_SPEED, BOAT_MASS};
+use crate::{FREEZING_POINT, WATER_BASE_TEMPERATURE, NUM_WARM_PATCHES, WARM_PATCH_MAX_WARMTH, MELT_RATE, THAW_INTERVAL, FREEZE_ATTEMPTS, FREEZE_CHANCE, FREEZE_MAX_CURRENT, FREEZE_MAX_WIND, CCD_MIN_TRAVEL, CCD_SKIN, BOAT_MAX_SUBSTEPS, PENETRATION_SLOP, PENETRATION_CORRECTION};
+use crate::{FUEL_CACHE_AMOUNT, FUEL_CACHE_RADIUS};
 use crate::keyboard_state::KeyboardState;
-use std::collections::HashMap;
-use crate::geometry::{reflect, lines_intersect, euc_distance};
-use std::time::Instant;
-use crate::render_gl::Program;
+use crate::spatial_hash::SpatialHash;
+use crate::entity::{EntityId, IdAllocator};
+use std::collections::{HashMap, BTreeMap};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use crate::geometry::euc_distance;
+use crate::collision::{Contact, sat_collision, time_of_impact, to_world};
+use crate::physics::{resolve_collision, separate};
+use crate::current::CurrentField;
+use crate::wind::Wind;
+use crate::temperature::Temperature;
+use crate::fuel::FuelCache;
+use crate::shapes::BergKind;
+
+// Running totals, mostly useful for headless runs
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Stats {
+    pub ticks: u64,
+    pub boat_collisions: u64,
+    pub ice_collisions: u64,
+    pub fractures: u64,
+    // Bergs that melted away, and new ones that froze
+    pub melted: u64,
+    pub frozen: u64,
+}
+
+// A pair of bodies that touched during the last tick
+#[derive(Debug, Clone)]
+pub struct Collision {
+    pub a: EntityId,
+    pub b: EntityId,
+    // Normal points from a into b
+    pub contact: Contact,
+}
 
+// How hard one berg pressed on the hull over a tick, as an impulse
+#[derive(Debug, Clone, Copy)]
+struct HullPush {
+    // Everything the solver applied, over all of the tick's substeps
+    impulse: Vector,
+    // Impulse that would have closed the overlap beyond slop within the tick, as of the last
+    // substep. Ice resting against the hull gets no impulse from the solver, but if it's been
+    // pushed in further than the slop it's pressing on the hull.
+    penalty: Vector,
+}
+
+// Pure simulation state - rendering lives in the renderer module so this can run without a display.
+// Serializable (see the snapshot module); lookup tables derived from ices are skipped and rebuilt on load.
+#[derive(Serialize, Deserialize)]
 pub struct World {
     size_x: u32,
     size_y: u32,
+    seed: u64,
+    // All randomness in the world comes from here, so a seed reproduces the same ice field.
+    // PCG rather than StdRng, since its output is stable across rand versions and it can be saved.
+    rng: Pcg64,
     ices: Vec<Ice>,
-    boat: Boat
+    boat: Boat,
+    current: CurrentField,
+    wind: Wind,
+    temperature: Temperature,
+    fuel_caches: Vec<FuelCache>,
+    ids: IdAllocator,
+    // Where each berg currently lives in ices
+    #[serde(skip)]
+    ice_index: HashMap<EntityId, usize>,
+    // Broad phase, indexed the same as ices
+    #[serde(skip, default = "World::new_grid")]
+    grid: SpatialHash,
+    #[serde(skip)]
+    collisions: Vec<Collision>,
+    // Bumped whenever a berg's shape changes or bergs come and go, so the renderer knows when to
+    // re-upload meshes
+    #[serde(skip)]
+    shape_generation: u64,
+    stats: Stats,
 }
 
 impl World {
-    pub fn new(size_x: u32, size_y: u32) -> World {
+    pub fn new(size_x: u32, size_y: u32, seed: u64) -> World {
         // Populate the world with some randomly positioned ice bergs
         let ice = Vec::new();
-        let boat = Boat::new(Vector{ x: (size_x / 2) as f32, y: (size_y / 2) as f32 }, BOAT_SIZE);
-        World{size_x, size_y, ices: ice, boat: boat}
+        let mut ids = IdAllocator::new();
+        let boat = Boat::new(ids.allocate(), Vector{ x: (size_x / 2) as f32, y: (size_y / 2) as f32 }, BOAT_SIZE);
+
+        // Gyres and warm water spread over the same area as the ice
+        let mut rng = Pcg64::seed_from_u64(seed);
+        let (min, max) = World::ice_area(size_x, size_y);
+        let current = CurrentField::random_gyres(&min, &max, NUM_GYRES, CURRENT_MAX_SPEED, &mut rng);
+        let wind = Wind::random(&mut rng);
+        let temperature = Temperature::random(&min, &max, WATER_BASE_TEMPERATURE, NUM_WARM_PATCHES, WARM_PATCH_MAX_WARMTH, &mut rng);
+        World{
+            size_x,
+            size_y,
+            seed,
+            rng,
+            ices: ice,
+            boat,
+            current,
+            wind,
+            temperature,
+            fuel_caches: Vec::new(),
+            ids,
+            ice_index: HashMap::new(),
+            grid: World::new_grid(),
+            collisions: Vec::new(),
+            shape_generation: 0,
+            stats: Stats::default(),
+        }
     }
 
-    // TODO: Make these controls more rudder-like (boat rotates)
-    pub fn key_w(&mut self) {
-        let dir = Vector{x:0.0, y:-1.0};
-        self.boat.direction = self.boat.direction.add(&dir.mul(BOAT_ACCELERATION));
+    // Corners of the area bergs are spread over
+    fn ice_area(size_x: u32, size_y: u32) -> (Vector, Vector) {
+        return (Vector{x: 0.0, y: -(size_y as f32)}, Vector{x: size_x as f32, y: size_y as f32});
+    }
+
+    fn new_grid() -> SpatialHash {
+        return SpatialHash::new(GRID_SIZE);
+    }
+
+    // Recomputes everything derived from ices, after they've been replaced wholesale (e.g. on load)
+    pub fn rebuild_indices(&mut self) {
+        self.grid = World::new_grid();
+        self.ice_index.clear();
+        for (i, ice) in self.ices.iter().enumerate() {
+            self.grid.update(i, &ice.position, ice.size as f32);
+            self.ice_index.insert(ice.id, i);
+        }
     }
 
-    // TODO: Rotation
+    // Throttle and rudder are only set while keys are held, see respond_to_input
+    pub fn key_w(&mut self) {
+        self.boat.throttle += 1.0;
+    }
     pub fn key_a(&mut self) {
-        /*
-        theta = deg2rad(angle);
-        cs = cos(theta);
-        sn = sin(theta);
-        px = x * cs - y * sn;
-        py = x * sn + y * cs;
-        */
-        let dir = Vector{x:-1.0, y:0.0};
-        self.boat.direction = self.boat.direction.add(&dir.mul(BOAT_ACCELERATION));
+        self.boat.rudder -= 1.0;
     }
     pub fn key_s(&mut self) {
-        let dir = Vector{x:0.0, y:1.0};
-        // TODO: Constants for keyboard input magic numbers
-        self.boat.direction = self.boat.direction.add(&dir.mul(BOAT_ACCELERATION));
+        self.boat.throttle -= BOAT_REVERSE_THROTTLE;
     }
-    // TODO: Rotation
     pub fn key_d(&mut self) {
-        let dir = Vector{x:1.0, y:0.0};
-        self.boat.direction = self.boat.direction.add(&dir.mul(BOAT_ACCELERATION));
+        self.boat.rudder += 1.0;
     }
 
     pub fn init_with_random_ice(&mut self, mut num_bergs: i32) {
         let margin = 10;
-        let mut rng = rand::thread_rng();
         while num_bergs > 0 {
-            let berg_size = rng.gen_range(BERG_MIN_SIZE, BERG_MAX_SIZE);
-            let x = rng.gen_range(berg_size + margin, self.size_x - (berg_size + margin));
-            let y = rng.gen_range(-1 * self.size_y as i32, self.size_y as i32 );
+            let berg_size = self.rng.gen_range(BERG_MIN_SIZE, BERG_MAX_SIZE);
+            let x = self.rng.gen_range(berg_size + margin, self.size_x - (berg_size + margin));
+            let y = self.rng.gen_range(-1 * self.size_y as i32, self.size_y as i32 );
 
             // Debugging, randomly pick a direction
-            let dir_x = rng.gen_range(-1.0,1.0);
-            let dir_y = rng.gen_range(-1.0,1.0);
-            let vel = rng.gen_range(0.0, 1.0);
-            let berg = Ice::new(Vector{x:x as f32, y:y as f32}, Vector{x:dir_x, y:dir_y}.mul(vel), berg_size);
+            let dir_x = self.rng.gen_range(-1.0,1.0);
+            let dir_y = self.rng.gen_range(-1.0,1.0);
+            let vel = self.rng.gen_range(0.0, 1.0);
+            let berg = Ice::new(Vector{x:x as f32, y:y as f32}, Vector{x:dir_x, y:dir_y}.mul(vel), berg_size, &mut self.rng);
 
             // let berg = Ice::new(Vector{x:x as f32, y:y as f32}, Vector{x:0.0, y:0.0}, berg_size);
-            let collisions = World::find_collisions_init(&self.ices, &berg);
+            let collisions = self.find_overlapping(&berg);
 
             if euc_distance(&self.boat.position, &berg.position) < (self.boat.size * 3 + *&berg.size) as f32 {
                continue;
             }
 
             if collisions.len() == 0 {
-                self.ices.push(berg);
+                self.add_ice(berg);
                 num_bergs -= 1;
                 println!("{:?} bergs remaining", num_bergs);
             }
         }
     }
 
+    pub fn init_with_random_fuel(&mut self, num_caches: u32) {
+        let (min, max) = World::ice_area(self.size_x, self.size_y);
+        for _ in 0..num_caches {
+            let position = Vector{x: self.rng.gen_range(min.x, max.x), y: self.rng.gen_range(min.y, max.y)};
+            self.add_fuel_cache(position, FUEL_CACHE_AMOUNT);
+        }
+    }
+
+    // Caches are the main lever for scenarios built around running short of coal
+    pub fn add_fuel_cache(&mut self, position: Vector, amount: f32) -> EntityId {
+        let id = self.ids.allocate();
+        self.fuel_caches.push(FuelCache{id, position, amount});
+        return id;
+    }
+
+    // Loads whatever the boat has room for from caches it's passing over. Emptied caches are gone.
+    fn collect_fuel(&mut self) {
+        let boat = &mut self.boat;
+        for cache in self.fuel_caches.iter_mut() {
+            if euc_distance(&boat.position, &cache.position) < FUEL_CACHE_RADIUS {
+                cache.amount -= boat.refuel(cache.amount);
+            }
+        }
+        self.fuel_caches.retain(|cache| cache.amount > 0.0);
+    }
+
     pub fn init_test(&mut self) {
-        self.ices.push(Ice::new(Vector{x: 1200.0, y: 1200.0}, Vector{x:10.0, y: 0.0}.mul(0.0), 300));
+        let berg = Ice::new(Vector{x: 1200.0, y: 1200.0}, Vector{x:10.0, y: 0.0}.mul(0.0), 300, &mut self.rng);
+        self.add_ice(berg);
         // self.ices.push(Ice::new(Vector{x: 1200.0, y: 200.0}, Vector{x:-10.0, y: 0.0}.mul(1.0), 100));
         // self.ices.push(Ice::new(Vector{x: 1200.0, y: 400.0}, Vector{x:-10.0, y: -5.0}.mul(1.0), 100));
     }
 
-    fn find_collisions<'a>(ices: Vec<&'a Ice>, ice: &Ice) -> Vec<&'a Ice> {
-        let collisions = ices.iter()
-            .filter(|other_ice| euc_distance(&other_ice.position, &ice.position) < (other_ice.size + ice.size) as f32)
-            .filter(|other_ice| &other_ice.position != &ice.position)
-            .map(|other_ice| *other_ice)
-            .collect();
-        return collisions;
+    // Takes ownership of the berg and gives it a fresh id
+    pub fn add_ice(&mut self, mut ice: Ice) -> EntityId {
+        ice.id = self.ids.allocate();
+        self.shape_generation += 1;
+        let index = self.ices.len();
+        self.grid.update(index, &ice.position, ice.size as f32);
+        self.ice_index.insert(ice.id, index);
+        self.ices.push(ice);
+        return self.ices[index].id;
     }
 
-    // TODO: Refactor this - quick fix for random berg init
-    fn find_collisions_init<'a>(ices: &'a Vec<Ice>, ice: &Ice) -> Vec<&'a Ice> {
-            let collisions = ices.iter()
-            .filter(|other_ice| euc_distance(&other_ice.position, &ice.position) < (other_ice.size + ice.size) as f32)
-            .filter(|other_ice| &other_ice.position != &ice.position)
-            .collect();
-        return collisions;
+    // Takes the berg out of the world. The last berg moves into its slot, so indices into ices
+    // aren't stable across this - ids are.
+    pub fn remove_ice(&mut self, id: EntityId) -> Option<Ice> {
+        let index = self.ice_index.remove(&id)?;
+        let ice = self.ices.swap_remove(index);
+        self.shape_generation += 1;
+        self.grid.swap_remove(index);
+        if let Some(moved) = self.ices.get(index) {
+            self.ice_index.insert(moved.id, index);
+        }
+        return Some(ice);
     }
 
-    fn get_boat_collision(boat: &Boat, ice: &Ice) -> Option<(Vector, Vector)> {
-        for i in 0..ice.perimeter.len() - 1 {
-            for k in 0..boat.perimeter.len() - 1 {
-                let l1_p1 = ice.position.add(ice.perimeter.get(i).unwrap());
-                let l1_p2 = ice.position.add(ice.perimeter.get(i + 1).unwrap());
+    // Breaks a berg the boat hit hard enough, replacing it with its pieces. Cracks run along the
+    // direction of the impact through the contact point; a hard enough hit cracks each half again.
+    // Pieces smaller than the smallest berg are lost.
+    fn fracture_berg(&mut self, id: EntityId, contact: &Vector, normal: &Vector, stress: f32) {
+        let angle = self.rng.gen_range(-FRACTURE_ANGLE_JITTER, FRACTURE_ANGLE_JITTER);
+        let ice = match self.get_ice(id) {
+            Some(ice) => ice,
+            None => return,
+        };
+        let crack = normal.rotate(angle);
+        let mut pieces = ice.fracture(contact, &crack);
+        if pieces.len() < 2 {
+            // Crack missed the berg
+            return;
+        }
+
+        if stress > 2.0 * ICE_HARDNESS {
+            let across = Vector{x: -crack.y, y: crack.x};
+            pieces = pieces.iter()
+                .flat_map(|piece| {
+                    let smaller = piece.fracture(&piece.position, &across);
+                    if smaller.len() < 2 { vec![piece.clone()] } else { smaller }
+                })
+                .collect();
+        }
 
-                let l2_p1 = boat.position.add(boat.perimeter.get(k).unwrap());
-                let l2_p2 = boat.position.add(boat.perimeter.get(k + 1).unwrap());
+        self.remove_ice(id);
+        self.stats.fractures += 1;
+        for piece in pieces {
+            if piece.size >= BERG_MIN_SIZE {
+                self.add_ice(piece);
+            }
+        }
+    }
 
-                if lines_intersect(l1_p1, l1_p2, l2_p1, l2_p2) {
-                    return Some((l2_p1, l2_p2));
+    // Bergs in warm water shrink, and ones that shrink below the smallest berg size are gone.
+    // New thin ice forms at random spots where the water is cold and calm. steps is the time since
+    // the last thaw in tuning ticks.
+    fn thaw(&mut self, steps: f32) {
+        let mut melted = Vec::new();
+        let mut reshaped = false;
+        for ice in self.ices.iter_mut() {
+            let warmth = self.temperature.at(&ice.position) - FREEZING_POINT;
+            if warmth > 0.0 {
+                ice.melt(warmth * MELT_RATE * steps);
+                reshaped = true;
+                if ice.radius() < BERG_MIN_SIZE as f32 {
+                    melted.push(ice.id);
                 }
             }
         }
-        return None;
+        // Once for the whole pass, so the renderer re-uploads outlines at most once per thaw
+        if reshaped {
+            self.shape_generation += 1;
+        }
+        for id in melted {
+            self.remove_ice(id);
+            self.stats.melted += 1;
+        }
+
+        if self.wind.velocity().length() > FREEZE_MAX_WIND {
+            return;
+        }
+        let (min, max) = World::ice_area(self.size_x, self.size_y);
+        for _ in 0..FREEZE_ATTEMPTS {
+            let position = Vector{x: self.rng.gen_range(min.x, max.x), y: self.rng.gen_range(min.y, max.y)};
+            let size = self.rng.gen_range(BERG_MIN_SIZE, 2 * BERG_MIN_SIZE);
+            let chance = self.rng.gen::<f32>();
+            let water = self.current.velocity_at(&position);
+            if chance > FREEZE_CHANCE || water.length() > FREEZE_MAX_CURRENT || self.temperature.at(&position) > FREEZING_POINT {
+                continue;
+            }
+            let ice = Ice::with_kind(position, water, size, BergKind::Floe, &mut self.rng);
+            let near_boat = euc_distance(&self.boat.position, &ice.position) < self.boat.radius() + ice.size as f32;
+            if !near_boat && self.find_overlapping(&ice).is_empty() {
+                self.add_ice(ice);
+                self.stats.frozen += 1;
+            }
+        }
+    }
+
+    fn bounds_overlap(ice_a: &Ice, ice_b: &Ice) -> bool {
+        return euc_distance(&ice_a.position, &ice_b.position) < (ice_a.size + ice_b.size) as f32;
+    }
+
+    // Indices of bergs whose bounding circles overlap a berg that isn't in the world yet
+    fn find_overlapping(&self, ice: &Ice) -> Vec<usize> {
+        return self.grid.query(&ice.position, ice.size as f32).into_iter()
+            .filter(|other| World::bounds_overlap(&self.ices[*other], ice))
+            .collect();
+    }
+
+    // Contact normal points from the boat into the ice
+    fn get_boat_collision(boat: &Boat, ice: &Ice) -> Option<Contact> {
+        return sat_collision(&to_world(&boat.position, &boat.perimeter), &to_world(&ice.position, &ice.perimeter));
+    }
+
+    // Exact polygon test for bergs whose bounding circles overlap. Contact normal points from ice_a into ice_b.
+    fn get_ice_collision(ice_a: &Ice, ice_b: &Ice) -> Option<Contact> {
+        return sat_collision(&to_world(&ice_a.position, &ice_a.perimeter), &to_world(&ice_b.position, &ice_b.perimeter));
     }
 
-    // Compares line segments making up bergs to see if they actually interact
-    fn is_real_collision(ice_a: &Ice, ice_b: &Ice) -> bool {
-        for i in 0..ice_a.perimeter.len() - 1 {
-            for k in 0..ice_b.perimeter.len() - 1 {
-                let l1_p1 = ice_a.position.add(ice_a.perimeter.get(i).unwrap());
-                let l1_p2 = ice_a.position.add(ice_a.perimeter.get(i + 1).unwrap());
 
-                let l2_p1 = ice_b.position.add(ice_b.perimeter.get(k).unwrap());
-                let l2_p2 = ice_b.position.add(ice_b.perimeter.get(k + 1).unwrap());
+    // Fraction of motion (0.0 - 1.0) a body can make before it hits one of the given obstacles
+    // (world space polygons), plus a little skin so it ends up just touching
+    fn sweep(polygon: &[Vector], motion: &Vector, obstacles: Vec<Vec<Vector>>) -> f32 {
+        let hit = obstacles.iter()
+            .filter_map(|obstacle| time_of_impact(polygon, obstacle, motion))
+            .fold(1.0, f32::min);
+        if hit >= 1.0 {
+            return 1.0;
+        }
+        return (hit + CCD_SKIN / motion.length()).min(1.0);
+    }
 
-                if lines_intersect(l1_p1, l1_p2, l2_p1, l2_p2) {
-                    return true;
+    // Resolves the boat against every berg it's touching. Bergs hit hard enough to crack are
+    // added to fractures rather than broken here, since that adds and removes bergs. How hard each
+    // berg presses on the hull is added to its entry in pushes, for measuring pressure. steps is
+    // the length of the whole tick in tuning ticks.
+    fn collide_boat(&mut self, fractures: &mut Vec<(EntityId, Vector, Vector, f32)>, pushes: &mut BTreeMap<EntityId, HullPush>, steps: f32) {
+        for i in self.grid.query(&self.boat.position, self.boat.radius()) {
+            let ice = &mut self.ices[i];
+            if let Some(contact) = World::get_boat_collision(&self.boat, ice) {
+                let impulse = resolve_collision(&mut self.boat, ice, &contact.center(), &contact.normal, ICE_RESTITUTION);
+                let reduced_mass = 1.0 / (1.0 / BOAT_MASS + 1.0 / ice.mass);
+                let excess = (contact.depth - PENETRATION_SLOP).max(0.0);
+                let push = pushes.entry(ice.id).or_insert(HullPush{impulse: Vector{x: 0.0, y: 0.0}, penalty: Vector{x: 0.0, y: 0.0}});
+                push.penalty = contact.normal.mul(reduced_mass * excess / steps);
+                separate(&mut self.boat, ice, &contact.normal, contact.depth, PENETRATION_SLOP, PENETRATION_CORRECTION);
+                if let Some(impulse) = impulse {
+                    // Kinetic energy the impulse took off the boat
+                    let energy = impulse.dot(&impulse) / (2.0 * BOAT_MASS);
+                    self.boat.damage(&contact.center(), energy);
+                    push.impulse = push.impulse.add(&impulse);
+                    let stress = impulse.length() / ice.size as f32;
+                    if stress > ICE_HARDNESS {
+                        fractures.push((ice.id, contact.center(), contact.normal, stress));
+                    }
+                }
+                // Only count each berg once, however many substeps it was touched in
+                let id = ice.id;
+                if !self.collisions.iter().any(|c| c.b == id) {
+                    self.stats.boat_collisions += 1;
+                    self.collisions.push(Collision{a: self.boat.id, b: id, contact});
                 }
             }
         }
-        return false;
     }
 
-
     fn respond_to_input(&mut self, keyboard_state: &KeyboardState) {
+        self.boat.throttle = 0.0;
+        self.boat.rudder = 0.0;
+        if self.is_game_over() {
+            return;
+        }
         if keyboard_state.w {
             self.key_w();
         }
@@ -158,210 +407,230 @@ impl World {
         }
     }
 
-    fn get_grid_region_bergs(grid: &HashMap<i32, HashMap<i32, Vec<Ice>>>, grid_x: i32, grid_y: i32) -> Vec<&Ice> {
-        let mut in_grid = Vec::new();
-        if let Some(col) = grid.get(&grid_x) {
-            if let Some(bergs) = col.get(&grid_y) {
-                for berg in bergs {
-                    in_grid.push(berg);
-                }
-            }
-        }
-        return in_grid;
+    // Mutable references to two distinct bergs, i < j
+    fn get_pair_mut(ices: &mut [Ice], i: usize, j: usize) -> (&mut Ice, &mut Ice) {
+        let (head, tail) = ices.split_at_mut(j);
+        return (&mut head[i], &mut tail[0]);
     }
 
-    // Called from event loop
-    pub fn tick(&mut self, keyboard_state: &KeyboardState) {
+    // Advances the simulation by dt seconds. Called from the event loop with a fixed dt.
+    pub fn tick(&mut self, keyboard_state: &KeyboardState, dt: f32) {
+        let steps = dt * TUNING_RATE;
 
         self.respond_to_input(keyboard_state);
+        self.stats.ticks += 1;
+        self.collisions.clear();
 
-        // Each tick, compute the current grid position of each iceberg
-        // TODO: Should be behind some kind of grid manager api
-        let mut grid = HashMap::new();
-        for ice in self.ices.iter() {
-            let (grid_x, grid_y) = ice.calc_grid();
-            let col = grid.entry(grid_x).or_insert(HashMap::new());
-            let row = col.entry(grid_y).or_insert(Vec::new());
-            row.push(ice.clone());
+        self.boat.save_state();
+        for ice in self.ices.iter_mut() {
+            ice.save_state();
         }
 
-        // Update the boat position
-        self.boat.position = self.boat.position.add(&self.boat.direction);
+        if self.stats.ticks.is_multiple_of(THAW_INTERVAL) {
+            self.thaw(steps * THAW_INTERVAL as f32);
+        }
 
-        let ices = self.ices.iter_mut();
-        for ice in ices {
+        // Wind pushes on everything above the waterline before the usual updates
+        self.wind.update(steps, &mut self.rng);
+        let wind = self.wind.velocity();
+        let profile = self.boat.wind_profile(&wind);
+        let push = self.wind.push(&self.boat.direction, profile, BOAT_MASS, steps);
+        self.boat.direction = self.boat.direction.add(&push);
+        for ice in self.ices.iter_mut() {
+            ice.direction = ice.direction.add(&self.wind.push(&ice.direction, ice.sail_area(), ice.mass, steps));
+        }
 
-            // Update ice position if it's colliding with the boat
-            if let Some((_p1, _p2)) = World::get_boat_collision(&self.boat, &ice) {
-                ice.direction = self.boat.direction.mul(1.5);
+        // Update the boat heading and velocity
+        let water = self.current.velocity_at(&self.boat.position);
+        self.boat.update(steps, &water);
+
+        // Boat rams ice. The move is swept, so the boat stops at the first berg in its way rather
+        // than passing through, resolves that hit and carries on with the rest of the tick.
+        let mut fractures = Vec::new();
+        let mut pushes = BTreeMap::new();
+        let mut remaining = steps;
+        for _ in 0..BOAT_MAX_SUBSTEPS {
+            let motion = self.boat.direction.mul(remaining);
+            let hull = to_world(&self.boat.position, &self.boat.perimeter);
+            let obstacles = self.grid.query(&self.boat.position.add(&motion.mul(0.5)), self.boat.radius() + motion.length() / 2.0)
+                .into_iter().map(|i| to_world(&self.ices[i].position, &self.ices[i].perimeter)).collect();
+            let fraction = World::sweep(&hull, &motion, obstacles);
+            self.boat.position = self.boat.position.add(&motion.mul(fraction));
+            self.collide_boat(&mut fractures, &mut pushes, steps);
+            remaining *= 1.0 - fraction;
+            if remaining <= 0.0 {
+                break;
             }
+        }
+        self.collect_fuel();
+
+        // Pack ice squeezing the hull, with one push per berg however many substeps touched it.
+        // Pushes from opposite sides cancel out in the net force on the boat, but they all add to
+        // the pressure on it.
+        let pushes: Vec<Vector> = pushes.values().map(|push| push.impulse.add(&push.penalty)).collect();
+        let total: f32 = pushes.iter().map(|push| push.length()).sum();
+        let net = pushes.iter().fold(Vector{x: 0.0, y: 0.0}, |sum, push| sum.add(push));
+        self.boat.update_pressure((total - net.length()) / steps, steps);
+        for (id, point, normal, stress) in fractures {
+            self.fracture_berg(id, &point, &normal, stress);
+        }
 
-            let (grid_x, grid_y) = ice.calc_grid();
-
-            // Colocated bergs - hopefully only a few
-            let mut possible_collisions = World::get_grid_region_bergs(&grid, grid_x, grid_y);
-            // let mut possible_collisions = Vec::new();
-            // possible_collisions.append(&mut others_in_grid.clone());
-
-            // Grid regions are squares, so the berg can be colliding with objects in up to three
-            // more grid regions adjacent to the one the center of the berg is in.
-            // THIS IS TRUE ONLY WHEN THE GRID SIZE IS GREATER THAN 2 * MAX_BERG_SIZE
-            // The bounds here are a little tricky. Our ice might be completely within its grid, but collide with
-            // another berg at the edge of an adjacent grid
-            let x_1 = (ice.position.x - ice.size as f32) < ((grid_x * GRID_SIZE as i32) + BERG_MAX_SIZE as i32) as f32;
-            let x_2 = (ice.position.x + ice.size as f32) > (((grid_x + 1) * GRID_SIZE as i32) - BERG_MAX_SIZE as i32) as f32;
-            let y_1 = (ice.position.y - ice.size as f32) < ((grid_y * GRID_SIZE as i32) + BERG_MAX_SIZE as i32) as f32;
-            let y_2 = (ice.position.y + ice.size as f32) > (((grid_y + 1) * GRID_SIZE as i32) - BERG_MAX_SIZE as i32) as f32;
-
-            if x_1 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x - 1, grid_y);
-                possible_collisions.append(&mut to_append.clone());
+        // Find all colliding pairs first, so both bergs in a pair see the same pre-collision state
+        let mut pairs = Vec::new();
+        for (i, j) in self.grid.query_pairs() {
+            let (ice_a, ice_b) = (&self.ices[i], &self.ices[j]);
+            if !World::bounds_overlap(ice_a, ice_b) {
+                continue;
             }
-            if x_2 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x + 1, grid_y);
-                possible_collisions.append(&mut to_append.clone());
-            }
-            if y_1 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x, grid_y - 1);
-                possible_collisions.append(&mut to_append.clone());
-            }
-            if y_2 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x, grid_y + 1);
-                possible_collisions.append(&mut to_append.clone());
-            }
-
-            // Upper left corner
-            if x_1 && y_1 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x - 1, grid_y - 1);
-                possible_collisions.append(&mut to_append.clone());
+            if let Some(contact) = World::get_ice_collision(ice_a, ice_b) {
+                pairs.push((i, j, contact));
             }
+        }
 
-            // Lower left corner
-            if x_1 && y_2 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x - 1, grid_y + 1);
-                possible_collisions.append(&mut to_append.clone());
+        // Resolve in index order rather than grid order, so results don't depend on the broad
+        // phase's history (and a world rebuilt from a snapshot carries on identically)
+        pairs.sort_by_key(|(i, j, _)| (*i, *j));
+        for (i, j, contact) in pairs {
+            let (ice_a, ice_b) = World::get_pair_mut(&mut self.ices, i, j);
+            if resolve_collision(ice_a, ice_b, &contact.center(), &contact.normal, ICE_RESTITUTION).is_some() {
+                self.stats.ice_collisions += 1;
             }
-
-            // Upper right corner
-            if x_2 && y_1 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x + 1, grid_y - 1);
-                possible_collisions.append(&mut to_append.clone());
+            // Earlier pairs may already have pushed one of these bergs, so measure the overlap again
+            // rather than separating by a depth found before anything moved
+            if let Some(overlap) = World::get_ice_collision(ice_a, ice_b) {
+                separate(ice_a, ice_b, &overlap.normal, overlap.depth, PENETRATION_SLOP, PENETRATION_CORRECTION);
             }
+            self.collisions.push(Collision{a: ice_a.id, b: ice_b.id, contact});
+        }
 
-            // Lower right corner
-            if x_2 && y_2 {
-                let to_append = World::get_grid_region_bergs(&grid, grid_x + 1, grid_y + 1);
-                possible_collisions.append(&mut to_append.clone());
+        // Move the ice. Fast bergs are swept against their neighbours and the boat, which has
+        // already made its move, and stop where they first touch; the collision is picked up
+        // next tick.
+        let hull = to_world(&self.boat.position, &self.boat.perimeter);
+        for i in 0..self.ices.len() {
+            let water = self.current.velocity_at(&self.ices[i].position);
+            self.ices[i].update(steps, &water);
+            let ice = &self.ices[i];
+            let motion = ice.direction.mul(steps);
+            let mut fraction = 1.0;
+            if motion.length() > CCD_MIN_TRAVEL {
+                let center = ice.position.add(&motion.mul(0.5));
+                let reach = ice.size as f32 + motion.length() / 2.0;
+                let mut obstacles: Vec<Vec<Vector>> = self.grid.query(&center, reach).into_iter()
+                    .filter(|j| *j != i)
+                    .map(|j| to_world(&self.ices[j].position, &self.ices[j].perimeter))
+                    .collect();
+                if euc_distance(&center, &self.boat.position) < reach + self.boat.radius() {
+                    obstacles.push(hull.clone());
+                }
+                fraction = World::sweep(&to_world(&ice.position, &ice.perimeter), &motion, obstacles);
             }
+            let ice = &mut self.ices[i];
+            ice.position = ice.position.add(&motion.mul(fraction));
+            self.grid.update(i, &ice.position, ice.size as f32);
+        }
+    }
 
-            // Collisions from circular bounding box
-            let collisions = World::find_collisions(possible_collisions, &ice);
-            for collision in collisions {
+    pub fn set_current(&mut self, current: CurrentField) {
+        self.current = current;
+    }
 
-                // Don't collide with yourself
-                // TODO: Use uuid instead of position
-                if collision.position.x == ice.position.x && collision.position.y == ice.position.y {
-                    continue;
-                }
+    pub fn get_current(&self) -> &CurrentField {
+        return &self.current;
+    }
 
-                if World::is_real_collision(&ice, &collision) {
-                    ice.direction = reflect(ice.position, ice.direction, collision.position, collision.direction);
-                }
-            }
+    pub fn get_wind(&self) -> &Wind {
+        return &self.wind;
+    }
 
-            // Collisions reduce velocity overall
-            ice.direction = ice.direction.mul(ICE_DECEL_FACTOR);
-            ice.position = ice.position.add(&ice.direction);
-        }
+    pub fn get_temperature(&self) -> &Temperature {
+        return &self.temperature;
     }
 
-    pub fn get_offset(&self) -> Vector {
-        return self.boat.position.sub(&Vector{x: (self.size_x / 2) as f32, y: (self.size_y / 2) as f32 });
+    pub fn get_seed(&self) -> u64 {
+        return self.seed;
     }
 
-    pub fn draw_gl(&self, program: &Program) {
-        let offset = self.boat.position.sub(&Vector{x: (self.size_x / 2) as f32, y: (self.size_y / 2) as f32 });
+    pub fn get_ices(&self) -> &Vec<Ice> {
+        return &self.ices;
+    }
 
-        // This is a vector of f32s, each group of six serially representing a vertex (xyz) and color info (rgb)
-        // Thus each group of eighteen (6*3) represents a triangle.
-        let mut vertices: Vec<f32> = Vec::new();
-        for berg in &self.ices {
-            let mut berg_verts = berg.get_vertices(&offset);
-            vertices.append(&mut berg_verts);
-        }
+    pub fn get_ice(&self, id: EntityId) -> Option<&Ice> {
+        return self.ice_index.get(&id).map(|i| &self.ices[*i]);
+    }
 
-        let mut boat_verts = self.boat.get_vertices(&offset);
-        vertices.append(&mut boat_verts);
+    // Broad phase grid the bergs are bucketed into
+    pub fn get_grid(&self) -> &SpatialHash {
+        return &self.grid;
+    }
 
-        let num_indices= vertices.len() as i32 / 6;
+    // Everything that touched during the last tick
+    pub fn get_collisions(&self) -> &Vec<Collision> {
+        return &self.collisions;
+    }
 
-        let mut vbo: gl::types::GLuint = 0;
-        unsafe {
-            gl::GenBuffers(1, &mut vbo);
-        }
+    pub fn get_fuel_caches(&self) -> &Vec<FuelCache> {
+        return &self.fuel_caches;
+    }
 
-        unsafe {
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,                                                       // target
-                (vertices.len() * std::mem::size_of::<f32>()) as gl::types::GLsizeiptr, // size of data in bytes
-                vertices.as_ptr() as *const gl::types::GLvoid, // pointer to data
-                gl::STATIC_DRAW,                               // usage
-            );
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-        }
+    pub fn get_boat(&self) -> &Boat {
+        return &self.boat;
+    }
 
-        // set up vertex array object
-        let mut vao: gl::types::GLuint = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut vao);
-        }
+    // The run is over once the ship sinks. The world carries on, but the helm no longer answers.
+    pub fn is_game_over(&self) -> bool {
+        return self.boat.is_sunk();
+    }
 
-        unsafe {
-            gl::BindVertexArray(vao);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+    pub fn get_shape_generation(&self) -> u64 {
+        return self.shape_generation;
+    }
 
-            gl::EnableVertexAttribArray(0); // this is "layout (location = 0)" in vertex shader
-            gl::VertexAttribPointer(
-                0,         // index of the generic vertex attribute ("layout (location = 0)")
-                3,         // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // normalized (int-to-float conversion)
-                (6 * std::mem::size_of::<f32>()) as gl::types::GLint, // stride (byte offset between consecutive attributes)
-                std::ptr::null(),                                     // offset of the first component
-            );
-            gl::EnableVertexAttribArray(1); // this is "layout (location = 0)" in vertex shader
-            gl::VertexAttribPointer(
-                1,         // index of the generic vertex attribute ("layout (location = 0)")
-                3,         // the number of components per generic vertex attribute
-                gl::FLOAT, // data type
-                gl::FALSE, // normalized (int-to-float conversion)
-                (6 * std::mem::size_of::<f32>()) as gl::types::GLint, // stride (byte offset between consecutive attributes)
-                (3 * std::mem::size_of::<f32>()) as *const gl::types::GLvoid, // offset of the first component
-            );
+    pub fn get_stats(&self) -> Stats {
+        return self.stats;
+    }
 
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
+    // Hash of the exact body state, for checking that two runs (e.g. a replay) ended up identical
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let boat = &self.boat;
+        for value in &[boat.position.x, boat.position.y, boat.direction.x, boat.direction.y, boat.heading] {
+            hasher.write_u32(value.to_bits());
         }
-        /*
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        for ice in &self.ices {
+            hasher.write_u32(ice.id.0);
+            for value in &[ice.position.x, ice.position.y, ice.direction.x, ice.direction.y, ice.rotation, ice.angular_velocity] {
+                hasher.write_u32(value.to_bits());
+            }
         }
-        */
+        return hasher.finish();
+    }
+}
 
-        // draw triangle
-        program.set_used();
-        unsafe {
-            gl::BindVertexArray(vao);
-            gl::DrawArrays(
-                gl::TRIANGLES, // mode
-                0,             // starting index in the enabled arrays
-                num_indices,             // number of indices to be rendered
-            );
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn melting_berg_despawns_below_min_size() {
+        let mut world = World::new(800, 600, 1);
+        // Warm everywhere, so nothing freezes
+        world.temperature = Temperature::uniform(FREEZING_POINT + 1.0);
+        let ice = Ice::with_kind(Vector{x: 100.0, y: -300.0}, Vector{x: 0.0, y: 0.0}, 20, BergKind::Floe, &mut world.rng);
+        let id = world.add_ice(ice);
+
+        // A hundredth of a unit off the radius each pass
+        let steps = 0.01 / MELT_RATE;
+        let mut passes = 0;
+        while let Some(ice) = world.get_ice(id) {
+            let radius = ice.radius();
+            assert!(radius >= BERG_MIN_SIZE as f32 - 0.01);
+            // size follows the outline down rather than staying at its spawn value
+            assert_eq!(ice.size, radius.ceil() as u32);
+            world.thaw(steps);
+            passes += 1;
+            assert!(passes < 2000, "berg never melted away");
         }
-
-        // TODO: Draw boat with opengl
-        // self.boat.draw_gl(program);
-
+        assert_eq!(world.get_stats().melted, 1);
     }
-}
\ No newline at end of file
+}